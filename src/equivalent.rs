@@ -0,0 +1,22 @@
+use std::borrow::Borrow;
+
+/// Key equivalence used for lookups, allowing a map to be queried with a type
+/// other than its key type.
+///
+/// A type implementing `Equivalent<K>` must hash exactly like the keys it is
+/// equivalent to. Every `Q` such that `K: Borrow<Q>` is equivalent to `K` by
+/// way of its `Eq` implementation.
+pub trait Equivalent<K: ?Sized> {
+    fn equivalent(&self, key: &K) -> bool;
+}
+
+impl<Q, K> Equivalent<K> for Q
+where
+    Q: Eq + ?Sized,
+    K: Borrow<Q> + ?Sized,
+{
+    #[inline]
+    fn equivalent(&self, key: &K) -> bool {
+        self == key.borrow()
+    }
+}
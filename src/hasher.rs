@@ -0,0 +1,176 @@
+use std::hash::{BuildHasher, Hasher};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// A seeded [`BuildHasher`] whose hashes only depend on the seed and the
+/// hashed value, so they are the same across runs and across targets.
+///
+/// It is meant for reproducible tests and snapshots, not for maps exposed to
+/// untrusted keys: unlike [`RandomState`](std::hash::RandomState) it offers
+/// no protection against collision attacks.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeterministicState {
+    seed: u64,
+}
+
+impl DeterministicState {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl BuildHasher for DeterministicState {
+    type Hasher = DeterministicHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        DeterministicHasher {
+            state: FNV_OFFSET ^ self.seed,
+        }
+    }
+}
+
+/// The hasher built by [`DeterministicState`]: FNV-1a over the little-endian
+/// bytes of the input, with a final avalanche step.
+#[derive(Clone, Debug)]
+pub struct DeterministicHasher {
+    state: u64,
+}
+
+impl Hasher for DeterministicHasher {
+    fn finish(&self) -> u64 {
+        // The splitmix64 finalizer, so that nearby inputs do not end up with
+        // nearby hashes.
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state = (self.state ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+}
+
+/// A hasher that hashes integers to themselves, for tests that need to pick
+/// the exact hashes of their keys.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct IdentityHasher(u64);
+
+#[cfg(test)]
+impl Hasher for IdentityHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 << 8) | u64::from(byte);
+        }
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.0 = n.into();
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.0 = n.into();
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.0 = n.into();
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = n;
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.0 = n as u64;
+    }
+}
+
+/// A hasher that hashes integers to their remainder modulo `N`, for testing
+/// how maps deal with keys whose hashes collide. With the default `N` of 1
+/// every value hashes to the same hash; with a larger `N` integers collide
+/// when they are congruent modulo `N`.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct CollidingHasher<const N: u64 = 1>(IdentityHasher);
+
+#[cfg(test)]
+impl<const N: u64> Hasher for CollidingHasher<N> {
+    fn finish(&self) -> u64 {
+        self.0.finish() % N
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.0.write_u8(n);
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.0.write_u16(n);
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.0.write_u32(n);
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0.write_u64(n);
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.0.write_usize(n);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::BuildHasher;
+
+    use super::DeterministicState;
+
+    #[test]
+    fn hashes_are_stable() {
+        let state = DeterministicState::new(42);
+        assert_eq!(state.hash_one(0u64), state.hash_one(0u64));
+        assert_eq!(state.hash_one(7usize), state.hash_one(7u64));
+        assert_ne!(state.hash_one("a"), state.hash_one("b"));
+        assert_ne!(
+            state.hash_one("a"),
+            DeterministicState::new(43).hash_one("a")
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use std::hash::{BuildHasher, Hash};
+
+use imbl::shared_ptr::SharedPointerKind;
+
+use crate::IndexMap;
+
+impl<K, V, S, P: SharedPointerKind> From<indexmap::IndexMap<K, V, S>> for IndexMap<K, V, S, P>
+where
+    K: Clone + Hash + Eq,
+    V: Clone,
+    S: Clone + BuildHasher,
+{
+    fn from(map: indexmap::IndexMap<K, V, S>) -> Self {
+        let mut out = Self::with_capacity_and_hasher(map.len(), map.hasher().clone());
+        out.extend(map);
+        out
+    }
+}
+
+impl<K, V, S, P: SharedPointerKind> From<IndexMap<K, V, S, P>> for indexmap::IndexMap<K, V, S>
+where
+    K: Clone + Hash + Eq,
+    V: Clone,
+    S: Clone + BuildHasher,
+{
+    fn from(map: IndexMap<K, V, S, P>) -> Self {
+        let mut out = Self::with_capacity_and_hasher(map.len(), map.hasher().clone());
+        out.extend(map);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::IndexMap;
+
+    #[test]
+    fn from_indexmap_preserves_order() {
+        let mut source: indexmap::IndexMap<&str, i32> = [("c", 3), ("a", 1), ("d", 4), ("b", 2)]
+            .into_iter()
+            .collect();
+        source.shift_remove(&"d");
+
+        let map: IndexMap<&str, i32> = source.clone().into();
+        assert!(map.iter().eq(source.iter()));
+        assert_eq!(map.get(&"b"), Some(&2));
+        assert_eq!(map.get(&"d"), None);
+
+        let back: indexmap::IndexMap<&str, i32> = map.into();
+        assert_eq!(back.as_slice(), source.as_slice());
+    }
+
+    #[test]
+    fn into_indexmap_skips_removed_entries() {
+        let map: IndexMap<i32, char> = [(5, 'e'), (1, 'a'), (3, 'c'), (2, 'b')]
+            .into_iter()
+            .collect();
+        let map = map.without(&1).without(&2);
+
+        let converted: indexmap::IndexMap<i32, char> = map.clone().into();
+        assert_eq!(converted.len(), 2);
+        assert!(converted.iter().eq(map.iter()));
+
+        let back: IndexMap<i32, char> = converted.into();
+        assert_eq!(back, map);
+    }
+}
@@ -1,8 +1,29 @@
-mod map;
-mod set;
+mod equivalent;
+mod hasher;
+pub mod map;
+pub mod set;
+
+#[cfg(feature = "indexmap-interop")]
+mod interop;
+
+#[cfg(feature = "rayon")]
+mod par;
+
+#[cfg(feature = "serde")]
+mod ser;
+
+#[doc(inline)]
+pub use equivalent::Equivalent;
+
+#[doc(inline)]
+pub use hasher::{DeterministicHasher, DeterministicState};
 
 #[doc(inline)]
-pub use map::IndexMap;
+pub use map::{
+    ArcIndexMap, Change, EntriesMut, Entry, EntryByRef, EntryRef, FromSortedError, IndexError,
+    IndexMap, KeyError, MapEditor, OccupiedEntry, OccupiedError, RawEntryBuilderMut, RawEntryMut,
+    RawVacantEntryMut, RenameError, VacantEntry, VacantEntryByRef,
+};
 
 #[doc(inline)]
-pub use set::IndexSet;
+pub use set::{ArcIndexSet, IndexSet};
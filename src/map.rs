@@ -1,15 +1,34 @@
 use core::fmt;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::hash::{BuildHasher, Hash, Hasher, RandomState};
+use std::iter::FusedIterator;
+use std::ops::{AddAssign, Bound, Index, RangeBounds};
 
-use imbl::shared_ptr::RcK;
+use imbl::shared_ptr::{ArcK, RcK, SharedPointerKind};
 
+use crate::{DeterministicState, Equivalent, IndexSet};
+
+mod editor;
+mod entry;
+mod raw_entry;
+
+pub use editor::MapEditor;
+pub use entry::{
+    EntriesMut, Entry, EntryByRef, EntryRef, OccupiedEntry, VacantEntry, VacantEntryByRef,
+};
+pub use raw_entry::{RawEntryBuilderMut, RawEntryMut, RawVacantEntryMut};
+
+/// The full 64-bit hash of a key, kept intact on every target so that keys
+/// whose hashes only differ in their upper bits stay distinct.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct HashValue(usize);
+pub(crate) struct HashValue(u64);
 
 impl HashValue {
     #[inline(always)]
     fn get(self) -> u64 {
-        self.0 as u64
+        self.0
     }
 }
 
@@ -22,20 +41,28 @@ impl Hash for HashValue {
 
 #[derive(Copy, Clone, Debug)]
 struct Bucket<K, V> {
+    hash: HashValue,
     key: K,
     value: V,
 }
 
-type Indices<S> = imbl::GenericHashMap<HashValue, usize, S, RcK>;
-type Entries<K, V> = imbl::GenericVector<Option<Bucket<K, V>>, RcK>;
+type Indices<S, P> = imbl::GenericHashMap<HashValue, usize, S, P>;
+type Entries<K, V, P> = imbl::GenericVector<Option<Bucket<K, V>>, P>;
+
+/// An [`IndexMap`] backed by atomically reference counted nodes, which can be
+/// sent to and shared between threads.
+pub type ArcIndexMap<K, V, S = RandomState> = IndexMap<K, V, S, ArcK>;
 
-pub struct IndexMap<K, V, S = RandomState> {
-    indices: Indices<S>,
-    entries: Entries<K, V>,
+pub struct IndexMap<K, V, S = RandomState, P: SharedPointerKind = RcK> {
+    indices: Indices<S, P>,
+    entries: Entries<K, V, P>,
     hash_builder: S,
+    len: usize,
+    capacity: usize,
 }
 
-impl<K, V, S> fmt::Debug for IndexMap<K, V, S>
+/// Formats the live entries in iteration order.
+impl<K, V, S, P: SharedPointerKind> fmt::Debug for IndexMap<K, V, S, P>
 where
     K: fmt::Debug,
     V: fmt::Debug,
@@ -45,26 +72,111 @@ where
     }
 }
 
-impl<K, V, S> PartialEq for IndexMap<K, V, S>
+/// Two maps are equal when they hold equal entries in the same order.
+///
+/// Maps with the same contents but a different insertion order are *not*
+/// equal, since their iterators yield different sequences.
+impl<K, V, S, P: SharedPointerKind> PartialEq for IndexMap<K, V, S, P>
 where
-    K: Hash + Eq,
+    K: PartialEq,
     V: PartialEq,
-    S: BuildHasher,
 {
     fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<K, V, S, P: SharedPointerKind> Eq for IndexMap<K, V, S, P>
+where
+    K: Eq,
+    V: Eq,
+{
+}
+
+/// An [`IndexMap`] equals a [`HashMap`] holding the same entries. Unlike
+/// comparisons between two [`IndexMap`]s, the order of the entries is not
+/// considered.
+impl<K, V, S, S2, P: SharedPointerKind> PartialEq<HashMap<K, V, S2>> for IndexMap<K, V, S, P>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    S2: BuildHasher,
+{
+    fn eq(&self, other: &HashMap<K, V, S2>) -> bool {
         self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
     }
 }
 
-impl<K, V, S> Eq for IndexMap<K, V, S>
+impl<K, V, S, S2, P: SharedPointerKind> PartialEq<IndexMap<K, V, S, P>> for HashMap<K, V, S2>
 where
     K: Hash + Eq,
     V: PartialEq,
-    S: BuildHasher,
+    S2: BuildHasher,
+{
+    fn eq(&self, other: &IndexMap<K, V, S, P>) -> bool {
+        other == self
+    }
+}
+
+/// An [`IndexMap`] equals a [`BTreeMap`] holding the same entries, regardless
+/// of their order.
+impl<K, V, S, P: SharedPointerKind> PartialEq<BTreeMap<K, V>> for IndexMap<K, V, S, P>
+where
+    K: Ord,
+    V: PartialEq,
+{
+    fn eq(&self, other: &BTreeMap<K, V>) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K, V, S, P: SharedPointerKind> PartialEq<IndexMap<K, V, S, P>> for BTreeMap<K, V>
+where
+    K: Ord,
+    V: PartialEq,
+{
+    fn eq(&self, other: &IndexMap<K, V, S, P>) -> bool {
+        other == self
+    }
+}
+
+/// Maps are compared lexicographically by their entries in iteration order.
+impl<K, V, S, P: SharedPointerKind> PartialOrd for IndexMap<K, V, S, P>
+where
+    K: PartialOrd,
+    V: PartialOrd,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<K, V, S, P: SharedPointerKind> Ord for IndexMap<K, V, S, P>
+where
+    K: Ord,
+    V: Ord,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+/// Maps are hashed in iteration order, consistent with their [`PartialEq`]
+/// implementation.
+impl<K, V, S, P: SharedPointerKind> Hash for IndexMap<K, V, S, P>
+where
+    K: Hash,
+    V: Hash,
 {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len);
+        for entry in self.iter() {
+            entry.hash(state);
+        }
+    }
 }
 
-impl<K, V, S> FromIterator<(K, V)> for IndexMap<K, V, S>
+impl<K, V, S, P: SharedPointerKind> FromIterator<(K, V)> for IndexMap<K, V, S, P>
 where
     S: Clone + Default + BuildHasher,
     K: Clone + Hash + Eq,
@@ -82,26 +194,88 @@ where
     }
 }
 
-impl<K, V, S> IntoIterator for IndexMap<K, V, S>
+impl<K, V, S, P: SharedPointerKind> Extend<(K, V)> for IndexMap<K, V, S, P>
+where
+    S: Clone + BuildHasher,
+    K: Clone + Hash + Eq,
+    V: Clone,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+/// Merges whole maps in, each in its own order. Keys already present are
+/// overwritten in place, and new keys are appended.
+impl<K, V, S, P: SharedPointerKind> Extend<IndexMap<K, V, S, P>> for IndexMap<K, V, S, P>
+where
+    S: Clone + BuildHasher,
+    K: Clone + Hash + Eq,
+    V: Clone,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = IndexMap<K, V, S, P>>,
+    {
+        for other in iter {
+            self.reserve(other.len());
+            for (key, value) in other {
+                self.insert(key, value);
+            }
+        }
+    }
+}
+
+impl<'a, K, V, S, P: SharedPointerKind> Extend<(&'a K, &'a V)> for IndexMap<K, V, S, P>
+where
+    S: Clone + BuildHasher,
+    K: Copy + Hash + Eq,
+    V: Copy,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (&'a K, &'a V)>,
+    {
+        self.extend(iter.into_iter().map(|(k, v)| (*k, *v)));
+    }
+}
+
+impl<K, V, S, P: SharedPointerKind> IntoIterator for IndexMap<K, V, S, P>
 where
     K: Clone,
     V: Clone,
 {
     type Item = (K, V);
-    type IntoIter = IntoIter<K, V>;
+    type IntoIter = IntoIter<K, V, P>;
 
     fn into_iter(self) -> Self::IntoIter {
         IntoIter {
             entries: self.entries.into_iter(),
+            remaining: self.len,
         }
     }
 }
 
-pub struct IntoIter<K, V> {
-    entries: imbl::vector::ConsumingIter<Option<Bucket<K, V>>, RcK>,
+impl<'a, K, V, S, P: SharedPointerKind> IntoIterator for &'a IndexMap<K, V, S, P> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V, P>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct IntoIter<K, V, P: SharedPointerKind = RcK> {
+    entries: imbl::vector::ConsumingIter<Option<Bucket<K, V>>, P>,
+    remaining: usize,
 }
 
-impl<K, V> Iterator for IntoIter<K, V>
+impl<K, V, P: SharedPointerKind> Iterator for IntoIter<K, V, P>
 where
     K: Clone,
     V: Clone,
@@ -109,11 +283,126 @@ where
     type Item = (K, V);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.entries.next().flatten().map(|b| (b.key, b.value))
+        let bucket = self.entries.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some((bucket.key, bucket.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V, P: SharedPointerKind> DoubleEndedIterator for IntoIter<K, V, P>
+where
+    K: Clone,
+    V: Clone,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let bucket = self.entries.by_ref().rev().flatten().next()?;
+        self.remaining -= 1;
+        Some((bucket.key, bucket.value))
+    }
+}
+
+impl<K, V, P: SharedPointerKind> ExactSizeIterator for IntoIter<K, V, P>
+where
+    K: Clone,
+    V: Clone,
+{
+}
+
+impl<K, V, P: SharedPointerKind> FusedIterator for IntoIter<K, V, P>
+where
+    K: Clone,
+    V: Clone,
+{
+}
+
+pub struct Iter<'a, K, V, P: SharedPointerKind = RcK> {
+    entries: imbl::vector::Iter<'a, Option<Bucket<K, V>>, P>,
+    remaining: usize,
+}
+
+impl<'a, K, V, P: SharedPointerKind + 'a> Iterator for Iter<'a, K, V, P> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bucket = self.entries.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some((&bucket.key, &bucket.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V, P: SharedPointerKind + 'a> DoubleEndedIterator for Iter<'a, K, V, P> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let bucket = self.entries.by_ref().rev().flatten().next()?;
+        self.remaining -= 1;
+        Some((&bucket.key, &bucket.value))
+    }
+}
+
+impl<'a, K, V, P: SharedPointerKind + 'a> ExactSizeIterator for Iter<'a, K, V, P> {}
+
+impl<'a, K, V, P: SharedPointerKind + 'a> FusedIterator for Iter<'a, K, V, P> {}
+
+pub struct Keys<'a, K, V, P: SharedPointerKind = RcK> {
+    inner: Iter<'a, K, V, P>,
+}
+
+impl<'a, K, V, P: SharedPointerKind + 'a> Iterator for Keys<'a, K, V, P> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V, P: SharedPointerKind + 'a> DoubleEndedIterator for Keys<'a, K, V, P> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<'a, K, V, P: SharedPointerKind + 'a> ExactSizeIterator for Keys<'a, K, V, P> {}
+
+impl<'a, K, V, P: SharedPointerKind + 'a> FusedIterator for Keys<'a, K, V, P> {}
+
+pub struct Values<'a, K, V, P: SharedPointerKind = RcK> {
+    inner: Iter<'a, K, V, P>,
+}
+
+impl<'a, K, V, P: SharedPointerKind + 'a> Iterator for Values<'a, K, V, P> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V, P: SharedPointerKind + 'a> DoubleEndedIterator for Values<'a, K, V, P> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(_, v)| v)
     }
 }
 
-impl<K, V, S> IndexMap<K, V, S>
+impl<'a, K, V, P: SharedPointerKind + 'a> ExactSizeIterator for Values<'a, K, V, P> {}
+
+impl<'a, K, V, P: SharedPointerKind + 'a> FusedIterator for Values<'a, K, V, P> {}
+
+impl<K, V, S, P: SharedPointerKind> IndexMap<K, V, S, P>
 where
     S: Clone + Default,
 {
@@ -122,16 +411,41 @@ where
         Self::with_hasher(S::default())
     }
 
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, S::default())
+    }
+
+    /// Creates an empty map with the same hasher as this one.
     pub fn new_from(&self) -> Self {
         Self {
             indices: Indices::new(),
             entries: Entries::new(),
             hash_builder: self.hash_builder.clone(),
+            len: 0,
+            capacity: 0,
+        }
+    }
+
+    /// Like [`IndexMap::new_from`], but the new map also expects to hold as
+    /// many entries as this one's [`IndexMap::capacity`].
+    pub fn new_from_with_capacity(&self) -> Self {
+        Self {
+            capacity: self.capacity(),
+            ..self.new_from()
         }
     }
 }
 
-impl<K, V, S> Default for IndexMap<K, V, S>
+impl<K, V, P: SharedPointerKind> IndexMap<K, V, DeterministicState, P> {
+    /// Creates an empty map whose keys are hashed with a
+    /// [`DeterministicState`] seeded with `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_hasher(DeterministicState::new(seed))
+    }
+}
+
+impl<K, V, S, P: SharedPointerKind> Default for IndexMap<K, V, S, P>
 where
     S: Clone + Default,
 {
@@ -141,9 +455,21 @@ where
     }
 }
 
-impl<K, V, S> IndexMap<K, V, S> {
+impl<K, V, S, P: SharedPointerKind> IndexMap<K, V, S, P> {
     #[inline]
     pub fn with_hasher(hash_builder: S) -> Self
+    where
+        S: Clone,
+    {
+        Self::with_capacity_and_hasher(0, hash_builder)
+    }
+
+    /// Creates an empty map that expects to hold `capacity` entries.
+    ///
+    /// The `imbl` structures backing the map allocate node by node and have
+    /// no way to reserve space up front, so the capacity is only recorded as
+    /// a hint and reported back by [`IndexMap::capacity`].
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self
     where
         S: Clone,
     {
@@ -151,414 +477,4583 @@ impl<K, V, S> IndexMap<K, V, S> {
             indices: Indices::with_hasher(hash_builder.clone()),
             entries: Entries::new(),
             hash_builder,
+            len: 0,
+            capacity,
         }
     }
 
     #[inline]
-    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
-        self.entries.iter().flatten().map(|b| (&b.key, &b.value))
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
     }
 
+    /// Returns the number of entries the map expects to hold, which is the
+    /// larger of the requested capacity and the number of occupied slots.
     #[inline]
-    pub fn len(&self) -> usize {
-        self.entries.len()
+    pub fn capacity(&self) -> usize {
+        self.capacity.max(self.entries.len())
+    }
+
+    /// Records that at least `additional` more entries are about to be
+    /// inserted, so that [`IndexMap::capacity`] covers them.
+    ///
+    /// As with [`IndexMap::with_capacity_and_hasher`], this is only a hint.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`.
+    pub fn reserve(&mut self, additional: usize) {
+        let capacity = self
+            .len
+            .checked_add(additional)
+            .expect("IndexMap::reserve: capacity overflow");
+        self.capacity = self.capacity.max(capacity);
+    }
+
+    /// Like [`IndexMap::reserve`], but counts the `additional` entries from
+    /// [`IndexMap::physical_len`] rather than from [`IndexMap::len`], so that
+    /// the hint also covers the empty slots left behind by removals.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`.
+    pub fn reserve_entries(&mut self, additional: usize) {
+        let capacity = self
+            .entries
+            .len()
+            .checked_add(additional)
+            .expect("IndexMap::reserve_entries: capacity overflow");
+        self.capacity = self.capacity.max(capacity);
     }
 
     #[inline]
-    pub fn is_empty(&self) -> bool {
-        self.entries.is_empty()
+    pub fn iter(&self) -> Iter<'_, K, V, P> {
+        Iter {
+            entries: self.entries.iter(),
+            remaining: self.len,
+        }
     }
-}
 
-impl<K, V, S> Clone for IndexMap<K, V, S>
-where
-    K: Clone,
-    V: Clone,
-    S: Clone,
-{
-    fn clone(&self) -> Self {
+    #[inline]
+    pub fn keys(&self) -> Keys<'_, K, V, P> {
+        Keys { inner: self.iter() }
+    }
+
+    #[inline]
+    pub fn values(&self) -> Values<'_, K, V, P> {
+        Values { inner: self.iter() }
+    }
+
+    /// Returns a new map with the same keys, in the same order, and each value
+    /// replaced by `f` applied to it. Keys are not rehashed: the new map
+    /// shares its index with this one.
+    pub fn map_values<W, F>(&self, mut f: F) -> IndexMap<K, W, S, P>
+    where
+        K: Clone,
+        W: Clone,
+        S: Clone,
+        F: FnMut(&V) -> W,
+    {
+        self.map_entries(|_, value| f(value))
+    }
+
+    /// Like [`IndexMap::map_values`], but `f` also gets the key.
+    pub(crate) fn map_entries<W, F>(&self, mut f: F) -> IndexMap<K, W, S, P>
+    where
+        K: Clone,
+        W: Clone,
+        S: Clone,
+        F: FnMut(&K, &V) -> W,
+    {
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| {
+                entry.as_ref().map(|bucket| Bucket {
+                    hash: bucket.hash,
+                    key: bucket.key.clone(),
+                    value: f(&bucket.key, &bucket.value),
+                })
+            })
+            .collect();
+
         IndexMap {
             indices: self.indices.clone(),
-            entries: self.entries.clone(),
+            entries,
             hash_builder: self.hash_builder.clone(),
+            len: self.len,
+            capacity: self.capacity,
         }
     }
 
-    fn clone_from(&mut self, other: &Self) {
-        self.indices.clone_from(&other.indices);
-        self.entries.clone_from(&other.entries);
-        self.hash_builder.clone_from(&other.hash_builder);
+    /// Returns the keys as a set, in the same order and with the same hasher.
+    /// Keys are not rehashed: the set shares its index with this map.
+    pub fn keys_as_set(&self) -> IndexSet<K, S, P>
+    where
+        K: Clone,
+        S: Clone,
+    {
+        IndexSet::from_map(self.map_values(|_| ()))
     }
-}
 
-impl<K, V, S> IndexMap<K, V, S>
-where
-    K: Hash + Eq,
-    S: BuildHasher,
-{
-    fn hash(&self, key: &K) -> HashValue {
-        HashValue(self.hash_builder.hash_one(key) as usize)
+    /// Returns the live entries, in order, as a persistent vector.
+    ///
+    /// Every entry is cloned into the new vector, so this takes linear time
+    /// and does not share structure with the map.
+    pub fn to_vector(&self) -> imbl::GenericVector<(K, V), P>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
     }
-}
 
-impl<K, V, S> IndexMap<K, V, S>
-where
-    K: Hash + Eq,
-    S: BuildHasher,
-{
-    pub fn get(&self, key: &K) -> Option<&V> {
-        let hash = self.hash(key);
-        self.indices
-            .get(&hash)
-            .and_then(|idx| self.entries.get(*idx))
-            .and_then(|e| e.as_ref())
-            .filter(|b| b.key == *key)
-            .map(|b| &b.value)
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        let bucket = self.entries.get(self.slot(index)?)?.as_ref()?;
+        Some((&bucket.key, &bucket.value))
     }
 
-    pub fn contains_key(&self, key: &K) -> bool {
-        let hash = self.hash(key);
-        self.indices
-            .get(&hash)
-            .and_then(|idx| self.entries.get(*idx))
-            .and_then(|e| e.as_ref())
-            .filter(|b| b.key == *key)
-            .is_some()
+    /// Like [`IndexMap::get_index`], but reports why there is no entry.
+    pub fn get_index_checked(&self, index: usize) -> Result<(&K, &V), IndexError> {
+        self.get_index(index).ok_or(IndexError::OutOfRange {
+            index,
+            len: self.len,
+        })
     }
-}
 
-impl<K, V, S> IndexMap<K, V, S>
-where
-    K: Clone + Hash + Eq,
-    V: Clone,
-    S: Clone + BuildHasher,
-{
-    pub fn insert(&mut self, key: K, value: V) {
-        let hash = self.hash(&key);
-        let bucket = Bucket { key, value };
+    /// Returns an iterator over the live entries whose logical positions fall
+    /// within `range`. Bounds past the end of the map are clamped to its
+    /// length.
+    pub fn get_range<R>(&self, range: R) -> Iter<'_, K, V, P>
+    where
+        R: RangeBounds<usize>,
+    {
+        let (start, end) = range_bounds(&range, self.len);
+        let start = start.min(self.len);
+        let end = end.clamp(start, self.len);
 
-        if let Some(idx) = self.indices.get(&hash) {
-            self.entries.get_mut(*idx).unwrap().replace(bucket);
-        } else {
-            let idx = self.entries.len();
-            self.indices.insert(hash, idx);
-            self.entries.push_back(Some(bucket));
+        let front = self.slot(start).unwrap_or(self.entries.len());
+        let back = self.slot(end).unwrap_or(self.entries.len());
+
+        let mut entries = self.entries.iter();
+        if front > 0 {
+            entries.nth(front - 1);
+        }
+        if back < self.entries.len() {
+            entries.nth_back(self.entries.len() - back - 1);
+        }
+
+        Iter {
+            entries,
+            remaining: end - start,
         }
     }
 
-    pub fn update(&self, key: K, value: V) -> Self {
-        let hash = self.hash(&key);
-        let bucket = Some(Bucket { key, value });
+    pub fn for_each<F>(&self, mut f: F)
+    where
+        F: FnMut(&K, &V),
+    {
+        self.iter().for_each(|(k, v)| f(k, v));
+    }
 
-        if let Some(idx) = self.indices.get(&hash) {
-            let entries = self.entries.update(*idx, bucket);
-            Self {
-                indices: self.indices.clone(),
-                entries,
-                hash_builder: self.hash_builder.clone(),
-            }
-        } else {
-            let idx = self.entries.len();
-            let indices = self.indices.update(hash, idx);
-            let mut entries = self.entries.clone();
-            entries.push_back(bucket);
+    /// Returns the number of live entries for which `f` returns `true`.
+    pub fn count_if<F>(&self, mut f: F) -> usize
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.iter().filter(|(k, v)| f(k, v)).count()
+    }
+
+    /// Returns `true` if `f` returns `true` for any live entry, stopping at
+    /// the first one that does.
+    pub fn any<F>(&self, mut f: F) -> bool
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.iter().any(|(k, v)| f(k, v))
+    }
+
+    /// Returns `true` if `f` returns `true` for every live entry, stopping at
+    /// the first one that does not.
+    pub fn all<F>(&self, mut f: F) -> bool
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.iter().all(|(k, v)| f(k, v))
+    }
+
+    pub fn fold<B, F>(&self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, &K, &V) -> B,
+    {
+        self.iter().fold(init, |acc, (k, v)| f(acc, k, v))
+    }
+
+    /// Like [`IndexMap::fold`], but stops at the first error returned by `f`.
+    pub fn try_fold<B, E, F>(&self, init: B, mut f: F) -> Result<B, E>
+    where
+        F: FnMut(B, &K, &V) -> Result<B, E>,
+    {
+        self.iter().try_fold(init, |acc, (k, v)| f(acc, k, v))
+    }
+
+    /// Returns the entry for which `f` returns the largest value, or `None`
+    /// if the map is empty. If several entries are equally large, the first
+    /// of them is returned.
+    pub fn max_by_key<B, F>(&self, f: F) -> Option<(&K, &V)>
+    where
+        B: Ord,
+        F: FnMut(&K, &V) -> B,
+    {
+        self.best_by_key(f, Ordering::Greater)
+    }
+
+    /// Returns the entry for which `f` returns the smallest value, or `None`
+    /// if the map is empty. If several entries are equally small, the first
+    /// of them is returned.
+    pub fn min_by_key<B, F>(&self, f: F) -> Option<(&K, &V)>
+    where
+        B: Ord,
+        F: FnMut(&K, &V) -> B,
+    {
+        self.best_by_key(f, Ordering::Less)
+    }
+
+    /// Returns the first entry whose key under `f` compares as `better` to
+    /// the keys of every entry before it.
+    fn best_by_key<B, F>(&self, mut f: F, better: Ordering) -> Option<(&K, &V)>
+    where
+        B: Ord,
+        F: FnMut(&K, &V) -> B,
+    {
+        let mut best: Option<(B, (&K, &V))> = None;
+        for (key, value) in self.iter() {
+            let candidate = f(key, value);
+            match &best {
+                Some((current, _)) if candidate.cmp(current) != better => {}
+                _ => best = Some((candidate, (key, value))),
+            }
+        }
+        best.map(|(_, entry)| entry)
+    }
+
+    /// Returns an iterator over the live entries in groups of `size`, the last
+    /// of which may be shorter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn chunks(&self, size: usize) -> impl Iterator<Item = Vec<(&K, &V)>> {
+        assert!(size != 0, "IndexMap::chunks: chunk size must be non-zero");
+        let mut iter = self.iter();
+        (0..self.len.div_ceil(size)).map(move |_| iter.by_ref().take(size).collect())
+    }
+
+    /// Returns an iterator over every run of `size` consecutive live entries.
+    /// Yields nothing if `size` is greater than the length of the map.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn windows(&self, size: usize) -> impl Iterator<Item = Vec<(&K, &V)>> {
+        assert!(size != 0, "IndexMap::windows: window size must be non-zero");
+        let entries: Vec<_> = self.iter().collect();
+        let count = (entries.len() + 1).saturating_sub(size);
+        (0..count).map(move |start| entries[start..start + size].to_vec())
+    }
+
+    /// Binary searches the keys for `key`, returning `Ok` with the logical
+    /// position of the key if found, or `Err` with the position at which it
+    /// could be inserted to keep the keys sorted.
+    ///
+    /// The result is only meaningful if the map is sorted by key, for
+    /// instance after [`IndexMap::sort_keys`].
+    ///
+    /// Time: O(log n) when the map has no empty slots, O(n) otherwise
+    pub fn binary_search_keys(&self, key: &K) -> Result<usize, usize>
+    where
+        K: Ord,
+    {
+        self.binary_search_by(|k, _| k.cmp(key))
+    }
+
+    /// Binary searches the entries with the comparator `f`, which should
+    /// return how an entry orders relative to the target.
+    ///
+    /// See [`IndexMap::binary_search_keys`] for the meaning of the result.
+    pub fn binary_search_by<F>(&self, mut f: F) -> Result<usize, usize>
+    where
+        F: FnMut(&K, &V) -> Ordering,
+    {
+        if self.len != self.entries.len() {
+            let live: Vec<_> = self.iter().collect();
+            return live.binary_search_by(|(k, v)| f(k, v));
+        }
+
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let bucket = self.entries[mid].as_ref().unwrap();
+            match f(&bucket.key, &bucket.value) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(low)
+    }
+
+    /// Returns `true` if the keys of the live entries are in ascending order.
+    pub fn is_sorted_by_keys(&self) -> bool
+    where
+        K: Ord,
+    {
+        self.is_sorted_by(|k1, _, k2, _| k1 <= k2)
+    }
+
+    /// Returns `true` if `f` holds for every pair of adjacent live entries,
+    /// called with the earlier entry first.
+    pub fn is_sorted_by<F>(&self, mut f: F) -> bool
+    where
+        F: FnMut(&K, &V, &K, &V) -> bool,
+    {
+        let mut iter = self.iter();
+        let Some(mut prev) = iter.next() else {
+            return true;
+        };
+        for next in iter {
+            if !f(prev.0, prev.1, next.0, next.1) {
+                return false;
+            }
+            prev = next;
+        }
+        true
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        self.iter().next_back()
+    }
+
+    /// Returns the number of live entries.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the number of slots backing the map, counting both the live
+    /// entries and the empty slots left behind by removals. This is never
+    /// less than [`IndexMap::len`], and equal to it after
+    /// [`IndexMap::compact`].
+    #[inline]
+    pub fn physical_len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the number of empty slots left behind by removals, which
+    /// [`IndexMap::compact`] would reclaim.
+    #[inline]
+    pub fn tombstone_count(&self) -> usize {
+        self.physical_len() - self.len
+    }
+
+    /// Returns the fraction of slots holding live entries, between `0.0` and
+    /// `1.0`. An empty map with no slots has a density of `1.0`.
+    pub fn density(&self) -> f64 {
+        if self.entries.is_empty() {
+            1.0
+        } else {
+            self.len as f64 / self.entries.len() as f64
+        }
+    }
+
+    /// Returns the slot in `entries` holding the live entry at logical
+    /// position `index`, skipping over the empty slots left by removals.
+    fn slot(&self, index: usize) -> Option<usize> {
+        if index >= self.len {
+            None
+        } else if self.len == self.entries.len() {
+            Some(index)
+        } else {
+            self.entries
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| entry.is_some())
+                .nth(index)
+                .map(|(idx, _)| idx)
+        }
+    }
+
+    /// Returns the logical position of the live entry stored in `slot`, the
+    /// inverse of [`IndexMap::slot`].
+    fn position(&self, slot: usize) -> usize {
+        if self.len == self.entries.len() {
+            slot
+        } else {
+            self.entries
+                .iter()
+                .take(slot)
+                .filter(|entry| entry.is_some())
+                .count()
+        }
+    }
+
+    /// Returns the physical slot, counting the empty slots left behind by
+    /// removals, of the live entry at logical position `logical`.
+    ///
+    /// Time: O(1) when the map has no empty slots, O(n) otherwise
+    pub fn logical_to_physical(&self, logical: usize) -> Option<usize> {
+        self.slot(logical)
+    }
+
+    /// Returns the logical position of the live entry in physical slot
+    /// `physical`, or `None` if that slot is empty or out of bounds. This is
+    /// the inverse of [`IndexMap::logical_to_physical`].
+    ///
+    /// Time: O(1) when the map has no empty slots, O(n) otherwise
+    pub fn physical_to_logical(&self, physical: usize) -> Option<usize> {
+        self.entries.get(physical)?.as_ref()?;
+        Some(self.position(physical))
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<K, V, S, P: SharedPointerKind> Clone for IndexMap<K, V, S, P>
+where
+    K: Clone,
+    V: Clone,
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        IndexMap {
+            indices: self.indices.clone(),
+            entries: self.entries.clone(),
+            hash_builder: self.hash_builder.clone(),
+            len: self.len,
+            capacity: self.capacity,
+        }
+    }
+
+    fn clone_from(&mut self, other: &Self) {
+        self.indices.clone_from(&other.indices);
+        self.entries.clone_from(&other.entries);
+        self.hash_builder.clone_from(&other.hash_builder);
+        self.len = other.len;
+        self.capacity = other.capacity;
+    }
+}
+
+impl<K, V, S, P: SharedPointerKind> IndexMap<K, V, S, P>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    fn hash<Q>(&self, key: &Q) -> HashValue
+    where
+        Q: Hash + ?Sized,
+    {
+        HashValue(self.hash_builder.hash_one(key))
+    }
+
+    /// Returns the slot in `entries` holding `key`, if any.
+    fn find<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.find_hashed(self.hash(key), key)
+    }
+
+    /// Like [`IndexMap::find`], for a key whose hash is already known.
+    fn find_hashed<Q>(&self, hash: HashValue, key: &Q) -> Option<usize>
+    where
+        Q: Equivalent<K> + ?Sized,
+    {
+        let idx = *self.indices.get(&hash)?;
+        self.entries
+            .get(idx)?
+            .as_ref()
+            .filter(|b| key.equivalent(&b.key))
+            .map(|_| idx)
+    }
+
+    /// Returns the logical position a new entry with `hash` is stored at by
+    /// [`IndexMap::put`]: that of the entry it replaces, if another key has
+    /// the same hash, or the end of the map otherwise.
+    fn vacant_index(&self, hash: HashValue) -> usize {
+        self.indices
+            .get(&hash)
+            .map_or(self.len, |&slot| self.position(slot))
+    }
+}
+
+impl<K, V, S, P: SharedPointerKind> IndexMap<K, V, S, P>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let idx = self.find(key)?;
+        self.entries[idx].as_ref().map(|b| &b.value)
+    }
+
+    /// Like [`IndexMap::get`], but returns an error if the key is missing.
+    pub fn get_checked(&self, key: &K) -> Result<&V, KeyError> {
+        self.get(key).ok_or(KeyError)
+    }
+
+    /// Like [`IndexMap::get`], but looks the key up through any borrowed form
+    /// of it, such as a `&str` for a `String` key.
+    pub fn get_by<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let idx = self.find(key)?;
+        self.entries[idx].as_ref().map(|b| &b.value)
+    }
+
+    /// Like [`IndexMap::get`], but looks the key up through any type that is
+    /// [`Equivalent`] to it.
+    pub fn get_equivalent<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let idx = self.find(key)?;
+        self.entries[idx].as_ref().map(|b| &b.value)
+    }
+
+    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+        let idx = self.find(key)?;
+        self.entries[idx].as_ref().map(|b| (&b.key, &b.value))
+    }
+
+    pub fn get_index_of(&self, key: &K) -> Option<usize> {
+        self.find(key).map(|slot| self.position(slot))
+    }
+
+    pub fn get_full(&self, key: &K) -> Option<(usize, &K, &V)> {
+        let slot = self.find(key)?;
+        let bucket = self.entries[slot].as_ref()?;
+        Some((self.position(slot), &bucket.key, &bucket.value))
+    }
+
+    /// Returns an iterator over the live entries following `key`, or over
+    /// all of them if `key` is absent.
+    pub fn iter_from(&self, key: &K) -> Iter<'_, K, V, P> {
+        let start = self.get_index_of(key).map_or(0, |index| index + 1);
+        self.get_range(start..)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find(key).is_some()
+    }
+
+    /// Like [`IndexMap::contains_key`], but takes any type that is
+    /// [`Equivalent`] to the key.
+    pub fn contains_equivalent<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    /// Like [`IndexMap::contains_key`], but takes any borrowed form of the
+    /// key.
+    pub fn contains_key_by<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    /// Returns an iterator over the live entries of `self`, in order, each
+    /// paired with the value for the same key in `other`, if any. Keys only
+    /// found in `other` are not visited.
+    pub fn zip_values<'a>(
+        &'a self,
+        other: &'a Self,
+    ) -> impl Iterator<Item = (&'a K, &'a V, Option<&'a V>)> {
+        self.iter().map(|(key, value)| (key, value, other.get(key)))
+    }
+
+    /// Returns the changes that turn `self` into `other`: the removed and
+    /// updated entries in `self`'s order, followed by the added entries in
+    /// `other`'s order. Entries whose values are equal are skipped.
+    pub fn diff<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = Change<'a, K, V>>
+    where
+        V: PartialEq,
+    {
+        let changed = self
+            .zip_values(other)
+            .filter_map(|(key, old, new)| match new {
+                None => Some(Change::Removed { key, value: old }),
+                Some(new) if new != old => Some(Change::Updated { key, old, new }),
+                Some(_) => None,
+            });
+        let added = other
+            .iter()
+            .filter(|(key, _)| !self.contains_key(key))
+            .map(|(key, value)| Change::Added { key, value });
+        changed.chain(added)
+    }
+}
+
+impl<K, V, S, P: SharedPointerKind> Index<&K> for IndexMap<K, V, S, P>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    type Output = V;
+
+    /// Returns a reference to the value stored for `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not present in the map.
+    fn index(&self, key: &K) -> &V {
+        self.get(key).expect("IndexMap: key not found")
+    }
+}
+
+impl<K, V, S, P: SharedPointerKind> Index<usize> for IndexMap<K, V, S, P> {
+    type Output = V;
+
+    /// Returns a reference to the value at logical position `index`, as
+    /// counted by [`IndexMap::get_index`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    fn index(&self, index: usize) -> &V {
+        match self.get_index(index) {
+            Some((_, value)) => value,
+            None => panic!("IndexMap: index out of bounds: {index}"),
+        }
+    }
+}
+
+impl<K, V, S, P: SharedPointerKind> IndexMap<K, V, S, P>
+where
+    K: Clone + Hash + Eq,
+    V: Clone,
+    S: Clone + BuildHasher,
+{
+    /// Inserts `value` under `key`, returning the previous value if the key
+    /// was already present. An existing key keeps its position.
+    ///
+    /// Only one entry is kept per hash: if a different key has the same
+    /// hash, its entry is replaced in place and `None` is returned, since
+    /// `key` itself was absent.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_full(key, value).1
+    }
+
+    /// Like [`IndexMap::insert`], but also returns the logical position of
+    /// the entry.
+    pub fn insert_full(&mut self, key: K, value: V) -> (usize, Option<V>) {
+        let hash = self.hash(&key);
+        let bucket = Bucket { hash, key, value };
+
+        match self.find_hashed(hash, &bucket.key) {
+            Some(idx) => {
+                let old = self.entries.get_mut(idx).unwrap().replace(bucket);
+                (self.position(idx), old.map(|old| old.value))
+            }
+            None => {
+                let index = self.vacant_index(hash);
+                self.put(bucket);
+                (index, None)
+            }
+        }
+    }
+
+    /// Builds a map by grouping the pairs of `iter` by key. The first time a
+    /// key is seen its value is created with `init`, and every item for the
+    /// key is then folded into it with `accumulate`. Keys keep the order in
+    /// which they first appeared.
+    pub fn from_iter_with<T, I, G, F>(iter: I, mut init: G, mut accumulate: F) -> Self
+    where
+        I: IntoIterator<Item = (K, T)>,
+        G: FnMut() -> V,
+        F: FnMut(&mut V, T),
+        S: Default,
+    {
+        let mut map = Self::new();
+        for (key, item) in iter {
+            accumulate(map.entry(key).or_insert_with(&mut init), item);
+        }
+        map
+    }
+
+    /// Builds a map from pairs whose keys are known to be sorted and unique,
+    /// appending each entry without comparing its key with existing ones.
+    ///
+    /// Only one entry is kept per hash, so a pair whose hash is already
+    /// taken, whether by a duplicate key or by a different key with the same
+    /// hash, replaces that entry in place, as with [`IndexMap::insert`]. Use
+    /// [`IndexMap::try_from_sorted_iter`] to reject such input instead.
+    pub fn from_sorted_unique_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        S: Default,
+    {
+        let mut map = Self::new();
+        for (key, value) in iter {
+            let hash = map.hash(&key);
+            map.put(Bucket { hash, key, value });
+        }
+        map
+    }
+
+    /// Like [`IndexMap::from_sorted_unique_iter`], but checks that every key
+    /// is strictly greater than the one before it, and that no two keys have
+    /// the same hash.
+    pub fn try_from_sorted_iter<I>(iter: I) -> Result<Self, FromSortedError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Ord,
+        S: Default,
+    {
+        let mut map = Self::new();
+        for (index, (key, value)) in iter.into_iter().enumerate() {
+            if map.last().is_some_and(|(last, _)| *last >= key) {
+                return Err(FromSortedError::Unsorted { index });
+            }
+            let hash = map.hash(&key);
+            if map.indices.contains_key(&hash) {
+                return Err(FromSortedError::HashCollision { index });
+            }
+            map.push(Bucket { hash, key, value });
+        }
+        Ok(map)
+    }
+
+    /// Returns the entry for `key`, for in-place manipulation.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S, P> {
+        let hash = self.hash(&key);
+        match self.find_hashed(hash, &key) {
+            Some(slot) => Entry::Occupied(OccupiedEntry::new(self, slot)),
+            None => Entry::Vacant(VacantEntry::new(self, hash, key)),
+        }
+    }
+
+    /// Like [`IndexMap::entry`], but looks the key up by reference. An owned
+    /// key is only made from it, with `From`, if the entry is vacant and a
+    /// value gets inserted.
+    pub fn entry_ref<'b, Q>(&mut self, key: &'b Q) -> EntryByRef<'_, 'b, K, Q, V, S, P>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hash(key);
+        match self.find_hashed(hash, key) {
+            Some(slot) => EntryByRef::Occupied(OccupiedEntry::new(self, slot)),
+            None => EntryByRef::Vacant(VacantEntryByRef::new(self, hash, key)),
+        }
+    }
+
+    /// Returns a builder for looking up an entry by a precomputed hash,
+    /// without hashing the key again.
+    pub fn raw_entry_mut(&mut self) -> RawEntryBuilderMut<'_, K, V, S, P> {
+        RawEntryBuilderMut::new(self)
+    }
+
+    /// Returns a cursor over handles to the live entries, in order, which can
+    /// mutate or remove each entry as it is visited.
+    ///
+    /// Removed entries leave empty slots behind during the pass, which are
+    /// compacted when the cursor is dropped; see [`EntriesMut`].
+    pub fn entries(&mut self) -> EntriesMut<'_, K, V, S, P> {
+        EntriesMut::new(self)
+    }
+
+    /// Returns the value for `key`, inserting the default value first if the
+    /// key is absent.
+    pub fn get_or_insert_default(&mut self, key: K) -> &mut V
+    where
+        V: Default,
+    {
+        self.entry(key).or_default()
+    }
+
+    /// Returns the logical position of `key` and its value, inserting
+    /// `value` at the end first if the key is absent.
+    pub fn get_or_insert_full(&mut self, key: K, value: V) -> (usize, &mut V) {
+        match self.entry(key) {
+            Entry::Occupied(entry) => (entry.index(), entry.into_mut()),
+            Entry::Vacant(entry) => (entry.index(), entry.insert(value)),
+        }
+    }
+
+    /// Returns the stored key equal to `key`, first inserting the key built
+    /// by `f`, with `value`, if there is none. The map is only searched once.
+    pub(crate) fn get_or_insert_key_with<F>(&mut self, key: &K, f: F, value: V) -> &K
+    where
+        F: FnOnce(&K) -> K,
+    {
+        let hash = self.hash(key);
+        let slot = match self.find_hashed(hash, key) {
+            Some(slot) => slot,
+            None => self.put(Bucket {
+                hash,
+                key: f(key),
+                value,
+            }),
+        };
+        &self.entries[slot].as_ref().unwrap().key
+    }
+
+    /// Adds `by` to the value for `key`, inserting `by` itself if the key is
+    /// absent, and returns the updated value.
+    pub fn increment(&mut self, key: K, by: V) -> &V
+    where
+        V: AddAssign,
+    {
+        match self.entry(key) {
+            Entry::Occupied(entry) => {
+                let value = entry.into_mut();
+                *value += by;
+                value
+            }
+            Entry::Vacant(entry) => entry.insert(by),
+        }
+    }
+
+    /// Returns a handle to the first live entry, if any.
+    pub fn first_entry(&mut self) -> Option<OccupiedEntry<'_, K, V, S, P>> {
+        let slot = self.slot(0)?;
+        Some(OccupiedEntry::new(self, slot))
+    }
+
+    /// Returns a handle to the last live entry, if any.
+    pub fn last_entry(&mut self) -> Option<OccupiedEntry<'_, K, V, S, P>> {
+        let slot = self.slot(self.len.checked_sub(1)?)?;
+        Some(OccupiedEntry::new(self, slot))
+    }
+
+    /// Inserts `value` under `key` only if the key is absent, returning a
+    /// mutable reference to the inserted value. If the key is already present
+    /// the map is left untouched and an [`OccupiedError`] is returned.
+    ///
+    /// Only one entry is kept per hash, so the entry of a different key with
+    /// the same hash is never overwritten either: the map is left untouched
+    /// and an [`OccupiedError`] holding that entry is returned.
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<&mut V, OccupiedError<'_, K, V>> {
+        let hash = self.hash(&key);
+
+        if let Some(&idx) = self.indices.get(&hash) {
+            let bucket = self.entries[idx].as_mut().unwrap();
+            return Err(OccupiedError {
+                key,
+                value,
+                existing_key: &bucket.key,
+                existing: &mut bucket.value,
+            });
+        }
+
+        let idx = self.push(Bucket { hash, key, value });
+        Ok(&mut self.entries[idx].as_mut().unwrap().value)
+    }
+
+    /// Returns mutable references to the values of all `keys` at once, or
+    /// `None` if any key is missing or if the same key is given twice.
+    pub fn get_many_mut<const N: usize>(&mut self, keys: [&K; N]) -> Option<[&mut V; N]> {
+        let mut slots = [0; N];
+        for (slot, key) in slots.iter_mut().zip(keys) {
+            *slot = self.find(key)?;
+        }
+        self.values_mut_at(slots)
+    }
+
+    /// Returns mutable references to the values at the logical positions
+    /// `indices` at once, or `None` if any position is out of bounds or if
+    /// the same position is given twice.
+    pub fn get_disjoint_indices_mut<const N: usize>(
+        &mut self,
+        indices: [usize; N],
+    ) -> Option<[&mut V; N]> {
+        let mut slots = [0; N];
+        for (slot, index) in slots.iter_mut().zip(indices) {
+            *slot = self.slot(index)?;
+        }
+        self.values_mut_at(slots)
+    }
+
+    /// Returns mutable references to the values in `slots`, or `None` if any
+    /// two of them are the same.
+    fn values_mut_at<const N: usize>(&mut self, slots: [usize; N]) -> Option<[&mut V; N]> {
+        let mut order: [usize; N] = std::array::from_fn(|i| i);
+        order.sort_unstable_by_key(|&i| slots[i]);
+        if order.windows(2).any(|w| slots[w[0]] == slots[w[1]]) {
+            return None;
+        }
+
+        // Hand out the slots in ascending order, splitting the focus after
+        // each one so that every reference borrows a disjoint part.
+        let mut values: [Option<&mut V>; N] = std::array::from_fn(|_| None);
+        let mut rest = self.entries.focus_mut();
+        let mut offset = 0;
+        for i in order {
+            let (head, tail) = rest.split_at(slots[i] + 1 - offset);
+            let entry = head.narrow(slots[i] - offset..).into_iter().next()?;
+            values[i] = Some(&mut entry.as_mut()?.value);
+            rest = tail;
+            offset = slots[i] + 1;
+        }
+
+        Some(values.map(Option::unwrap))
+    }
+
+    /// Inserts `value` under `key` at logical position `index`, shifting the
+    /// following entries back. If the key is already present its entry is
+    /// moved to `index`, and the previous value is returned.
+    ///
+    /// Only one entry is kept per hash: if a different key has the same
+    /// hash, its entry is removed, as with [`IndexMap::shift_remove`], and
+    /// `None` is returned.
+    ///
+    /// Time: O(n)
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length of the map, not counting
+    /// the entry that makes way for `key`.
+    pub fn insert_at(&mut self, index: usize, key: K, value: V) -> Option<V> {
+        let hash = self.hash(&key);
+        let taken = self.indices.get(&hash).copied();
+        let len = self.len - usize::from(taken.is_some());
+        assert!(
+            index <= len,
+            "IndexMap::insert_at: index {index} out of bounds for length {len}"
+        );
+
+        let old = taken
+            .map(|slot| self.shift_remove_slot(slot))
+            .filter(|bucket| bucket.key == key)
+            .map(|bucket| bucket.value);
+        let slot = self.slot(index).unwrap_or(self.entries.len());
+        self.entries.insert(slot, Some(Bucket { hash, key, value }));
+        self.len = grow_len(self.len);
+        self.reindex_from(slot);
+        old
+    }
+
+    /// Inserts `value` under `key` immediately before `anchor`, moving the
+    /// entry for `key` there if it already exists. Returns `false`, leaving
+    /// the map untouched, if `anchor` is absent.
+    ///
+    /// As with [`IndexMap::insert_at`], the entry of a different key with the
+    /// same hash is removed.
+    pub fn insert_before(&mut self, anchor: &K, key: K, value: V) -> bool {
+        match self.get_index_of(anchor) {
+            Some(index) => {
+                self.insert_near(index, key, value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Inserts `value` under `key` immediately after `anchor`, moving the
+    /// entry for `key` there if it already exists. Returns `false`, leaving
+    /// the map untouched, if `anchor` is absent.
+    ///
+    /// As with [`IndexMap::insert_at`], the entry of a different key with the
+    /// same hash is removed.
+    pub fn insert_after(&mut self, anchor: &K, key: K, value: V) -> bool {
+        match self.get_index_of(anchor) {
+            Some(index) => {
+                let index = index
+                    .checked_add(1)
+                    .expect("IndexMap::insert_after: index overflow");
+                self.insert_near(index, key, value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Inserts the entry so that it ends up just before what is currently at
+    /// logical position `index`, accounting for the removal of the entry
+    /// that makes way for it: that of the key itself, or of a different key
+    /// with the same hash.
+    fn insert_near(&mut self, mut index: usize, key: K, value: V) {
+        let hash = self.hash(&key);
+        if self
+            .indices
+            .get(&hash)
+            .is_some_and(|&slot| self.position(slot) < index)
+        {
+            index -= 1;
+        }
+        self.insert_at(index, key, value);
+    }
+
+    /// Renames the key `old` to `new`, keeping the entry's position and
+    /// value.
+    ///
+    /// Only one entry is kept per hash, so if a different key has the same
+    /// hash as `new`, its entry is removed.
+    pub fn replace_key(&mut self, old: &K, new: K) -> Result<(), RenameError> {
+        let idx = self.find(old).ok_or(RenameError::NotFound)?;
+        let hash = self.hash(&new);
+        if self
+            .find_hashed(hash, &new)
+            .is_some_and(|other| other != idx)
+        {
+            return Err(RenameError::AlreadyExists);
+        }
+
+        if let Some(other) = self.indices.get(&hash).copied() {
+            if other != idx {
+                self.entries.set(other, None);
+                self.len -= 1;
+            }
+        }
+
+        let bucket = self.entries[idx].as_mut().unwrap();
+        let old_hash = std::mem::replace(&mut bucket.hash, hash);
+        bucket.key = new;
+        self.indices.remove(&old_hash);
+        self.indices.insert(hash, idx);
+        Ok(())
+    }
+
+    pub fn update(&self, key: K, value: V) -> Self {
+        let hash = self.hash(&key);
+        let bucket = Some(Bucket { hash, key, value });
+
+        if let Some(idx) = self.indices.get(&hash) {
+            let entries = self.entries.update(*idx, bucket);
+            Self {
+                indices: self.indices.clone(),
+                entries,
+                hash_builder: self.hash_builder.clone(),
+                len: self.len,
+                capacity: self.capacity,
+            }
+        } else {
+            let idx = self.entries.len();
+            let indices = self.indices.update(hash, idx);
+            let mut entries = self.entries.clone();
+            entries.push_back(bucket);
+
+            Self {
+                indices,
+                entries,
+                hash_builder: self.hash_builder.clone(),
+                len: grow_len(self.len),
+                capacity: self.capacity,
+            }
+        }
+    }
+
+    /// Returns a new map with the edits made by `f` applied. The edits all
+    /// go to a single working copy of this map, so a batch of them is
+    /// cheaper than the equivalent chain of [`IndexMap::update`] and
+    /// [`IndexMap::without`] calls.
+    pub fn edit<F>(&self, f: F) -> Self
+    where
+        F: FnOnce(&mut MapEditor<K, V, S, P>),
+    {
+        let mut editor = MapEditor::new(self.clone());
+        f(&mut editor);
+        editor.into_map()
+    }
+
+    /// Returns a new map with every pair from `iter` inserted, in order. The
+    /// map is copied once up front rather than once per pair.
+    pub fn update_many<I>(&self, iter: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut out = self.clone();
+        out.extend(iter);
+        out
+    }
+
+    pub fn without(&self, key: &K) -> Self {
+        self.without_by(key)
+    }
+
+    /// Like [`IndexMap::without`], but takes any borrowed form of the key.
+    pub fn without_by<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if let Some(idx) = self.find(key) {
+            let indices = self.indices.without(&self.hash(key));
+            let entries = self.entries.update(idx, None);
+
+            Self {
+                indices,
+                entries,
+                hash_builder: self.hash_builder.clone(),
+                len: self.len - 1,
+                capacity: self.capacity,
+            }
+        } else {
+            self.clone()
+        }
+    }
+
+    /// Returns a new map without any of `keys`, keeping the order of the
+    /// remaining entries. The keys are all removed from a single copy of the
+    /// map, which is then compacted once.
+    pub fn without_all<'a, I>(&self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = &'a K>,
+        K: 'a,
+    {
+        let mut out = self.clone();
+        for key in keys {
+            out.remove(key);
+        }
+        out.compact();
+        out
+    }
+
+    /// Removes `key`, leaving its slot empty so that the positions of the
+    /// remaining entries are unaffected. This is the in-place counterpart of
+    /// [`IndexMap::without`].
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let idx = self.find(key)?;
+        let bucket = self.entries.set(idx, None)?;
+        self.indices.remove(&bucket.hash);
+        self.len -= 1;
+        Some(bucket.value)
+    }
+
+    /// Removes `key` and shifts every following entry down by one slot,
+    /// preserving the order of the remaining entries.
+    ///
+    /// Time: O(n)
+    pub fn shift_remove(&mut self, key: &K) -> Option<V> {
+        let slot = self.find(key)?;
+        Some(self.shift_remove_slot(slot).value)
+    }
+
+    /// Removes `key` and moves the last entry into its slot. This does not
+    /// preserve the order of the remaining entries.
+    ///
+    /// Time: O(log n)
+    pub fn swap_remove(&mut self, key: &K) -> Option<V> {
+        let slot = self.find(key)?;
+        Some(self.swap_remove_slot(slot).value)
+    }
+
+    /// Removes the entry at logical position `index` like
+    /// [`IndexMap::shift_remove`], returning it.
+    ///
+    /// Time: O(n)
+    pub fn shift_remove_index(&mut self, index: usize) -> Option<(K, V)> {
+        let slot = self.slot(index)?;
+        let bucket = self.shift_remove_slot(slot);
+        Some((bucket.key, bucket.value))
+    }
+
+    /// Removes the entry at logical position `index` like
+    /// [`IndexMap::swap_remove`], returning it.
+    pub fn swap_remove_index(&mut self, index: usize) -> Option<(K, V)> {
+        let slot = self.slot(index)?;
+        let bucket = self.swap_remove_slot(slot);
+        Some((bucket.key, bucket.value))
+    }
+
+    /// Removes and returns the last entry.
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        self.trim_back();
+        let bucket = self.entries.pop_back().flatten()?;
+        self.indices.remove(&bucket.hash);
+        self.len -= 1;
+        Some((bucket.key, bucket.value))
+    }
+
+    /// Returns the first entry together with a new map of the remaining
+    /// ones, or `None` if the map is empty. The rest shares its structure
+    /// with this map.
+    pub fn split_first(&self) -> Option<((&K, &V), Self)> {
+        let slot = self.entries.iter().position(Option::is_some)?;
+        Some(self.split_slot(slot))
+    }
+
+    /// Returns the last entry together with a new map of the remaining ones,
+    /// or `None` if the map is empty. The rest shares its structure with
+    /// this map.
+    pub fn split_last(&self) -> Option<((&K, &V), Self)> {
+        let slot = self.entries.iter().rposition(Option::is_some)?;
+        let (last, mut rest) = self.split_slot(slot);
+        rest.trim_back();
+        Some((last, rest))
+    }
+
+    /// Removes every entry, keeping the hasher and capacity hint.
+    pub fn clear(&mut self) {
+        self.indices.clear();
+        self.entries.clear();
+        self.len = 0;
+    }
+
+    /// Removes every entry, returning them in order. The map is left empty
+    /// but keeps its hasher and capacity hint, ready to be reused.
+    pub fn drain(&mut self) -> IntoIter<K, V, P> {
+        let remaining = std::mem::take(&mut self.len);
+        self.indices.clear();
+        IntoIter {
+            entries: std::mem::take(&mut self.entries).into_iter(),
+            remaining,
+        }
+    }
+
+    /// Moves every entry of `other` into this map, leaving `other` empty.
+    /// Keys already present are overwritten in place, and new keys are
+    /// appended in `other`'s order.
+    pub fn append(&mut self, other: &mut Self) {
+        self.extend(other.drain());
+    }
+
+    pub fn shift_without(&self, key: &K) -> Self {
+        let mut out = self.clone();
+        out.shift_remove(key);
+        out
+    }
+
+    pub fn swap_without(&self, key: &K) -> Self {
+        let mut out = self.clone();
+        out.swap_remove(key);
+        out
+    }
+
+    /// Combines `self` with `other`, resolving keys present in both with `f`.
+    ///
+    /// Entries keep their position from `self`, and keys only found in
+    /// `other` are appended in `other`'s order.
+    pub fn merge_with<F>(&self, other: &Self, f: F) -> Self
+    where
+        F: Fn(&K, &V, &V) -> V,
+    {
+        let mut out = self.clone();
+        for (key, value) in other.iter() {
+            let value = match self.get(key) {
+                Some(existing) => f(key, existing, value),
+                None => value.clone(),
+            };
+            out.insert(key.clone(), value);
+        }
+        out
+    }
+
+    /// Returns the entries of `self` whose keys are also in `other`, with
+    /// the two values combined by `f`, in `self`'s order.
+    pub fn intersection_with<F>(&self, other: &Self, f: F) -> Self
+    where
+        F: Fn(&K, &V, &V) -> V,
+    {
+        let mut out = Self::with_hasher(self.hash_builder.clone());
+        for (key, value) in self.iter() {
+            if let Some(theirs) = other.get(key) {
+                out.insert(key.clone(), f(key, value, theirs));
+            }
+        }
+        out
+    }
+
+    /// Returns the entries of `self` whose keys are not in `other`, in
+    /// `self`'s order.
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut out = self.clone();
+        out.retain_keys(|key| !other.contains_key(key));
+        out
+    }
+
+    /// Keeps the first `len` entries and drops the rest. Does nothing if the
+    /// map holds `len` entries or fewer.
+    pub fn truncate(&mut self, len: usize) {
+        let Some(slot) = self.slot(len) else {
+            return;
+        };
+
+        for bucket in self.entries.split_off(slot).into_iter().flatten() {
+            self.indices.remove(&bucket.hash);
+        }
+        self.len = len;
+    }
+
+    /// Splits the map in two at logical position `at`. `self` keeps the
+    /// entries before `at` and the entries from `at` onwards are returned in
+    /// a new map sharing the same hasher.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert!(
+            at <= self.len,
+            "IndexMap::split_off: index {at} out of bounds for length {}",
+            self.len
+        );
+
+        let slot = self.slot(at).unwrap_or(self.entries.len());
+        let mut out = Self::with_hasher(self.hash_builder.clone());
+        out.entries = self.entries.split_off(slot);
+        out.reindex();
+
+        for bucket in out.entries.iter().flatten() {
+            self.indices.remove(&bucket.hash);
+        }
+        self.len = at;
+        out
+    }
+
+    /// Removes the entries at the logical positions in `range`, shifting the
+    /// following entries down, and returns the removed entries in order.
+    ///
+    /// Time: O(n)
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range starts after it ends or ends after `len`.
+    pub fn drain_range<R>(&mut self, range: R) -> IntoIter<K, V, P>
+    where
+        R: RangeBounds<usize>,
+    {
+        let (start, end) = range_bounds(&range, self.len);
+        assert!(
+            start <= end && end <= self.len,
+            "IndexMap::drain_range: range {start}..{end} out of bounds for length {}",
+            self.len
+        );
+
+        let front = self.slot(start).unwrap_or(self.entries.len());
+        let back = self.slot(end).unwrap_or(self.entries.len());
+        let tail = self.entries.split_off(back);
+        let drained = self.entries.split_off(front);
+        self.entries.append(tail);
+
+        for bucket in drained.iter().flatten() {
+            self.indices.remove(&bucket.hash);
+        }
+        self.len -= end - start;
+        self.reindex_from(front);
+
+        IntoIter {
+            entries: drained.into_iter(),
+            remaining: end - start,
+        }
+    }
+
+    /// Moves the entry at logical position `from` to position `to`, shifting
+    /// the entries in between by one.
+    ///
+    /// Time: O(n)
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` or `to` is out of bounds.
+    pub fn move_index(&mut self, from: usize, to: usize) {
+        let from = self
+            .slot(from)
+            .expect("IndexMap::move_index: index out of bounds");
+        let to = self
+            .slot(to)
+            .expect("IndexMap::move_index: index out of bounds");
+
+        let bucket = self.entries.remove(from);
+        self.entries.insert(to, bucket);
+        self.reindex_from(from.min(to));
+    }
+
+    /// Reverses the order of the entries, dropping the empty slots left
+    /// behind by removals in the process.
+    ///
+    /// Time: O(n)
+    pub fn reverse(&mut self) {
+        self.entries = std::mem::take(&mut self.entries)
+            .into_iter()
+            .rev()
+            .flatten()
+            .map(Some)
+            .collect();
+        self.reindex();
+    }
+
+    /// Rotates the entries so that the one at logical position `n % len`
+    /// comes first, wrapping the preceding entries around to the end.
+    ///
+    /// Time: O(n)
+    pub fn rotate_left(&mut self, n: usize) {
+        if self.len == 0 {
+            return;
+        }
+        self.compact();
+
+        let n = n % self.len;
+        if n > 0 {
+            let mut entries = self.entries.split_off(n);
+            entries.append(std::mem::take(&mut self.entries));
+            self.entries = entries;
+            self.reindex();
+        }
+    }
+
+    /// Rotates the entries so that the last `n % len` of them come first.
+    ///
+    /// Time: O(n)
+    pub fn rotate_right(&mut self, n: usize) {
+        if self.len > 0 {
+            self.rotate_left(self.len - n % self.len);
+        }
+    }
+
+    /// Swaps the entries at logical positions `a` and `b`.
+    ///
+    /// Time: O(log n) when the map has no empty slots, O(n) otherwise
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` or `b` is out of bounds.
+    pub fn swap_indices(&mut self, a: usize, b: usize) {
+        let a = self
+            .slot(a)
+            .expect("IndexMap::swap_indices: index out of bounds");
+        let b = self
+            .slot(b)
+            .expect("IndexMap::swap_indices: index out of bounds");
+
+        self.entries.swap(a, b);
+        for idx in [a, b] {
+            if let Some(bucket) = &self.entries[idx] {
+                self.indices.insert(bucket.hash, idx);
+            }
+        }
+    }
+
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.entries
+            .retain(|e| e.as_ref().is_some_and(|b| f(&b.key, &b.value)));
+        self.reindex();
+    }
+
+    /// Like [`IndexMap::retain`], but only looks at the keys.
+    pub fn retain_keys<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K) -> bool,
+    {
+        self.retain(|k, _| f(k));
+    }
+
+    /// Like [`IndexMap::retain`], but only looks at the values.
+    pub fn retain_values<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&V) -> bool,
+    {
+        self.retain(|_, v| f(v));
+    }
+
+    /// Splits the map into the entries for which `f` returns `true` and those
+    /// for which it returns `false`, both in their original order.
+    pub fn partition<F>(&self, mut f: F) -> (Self, Self)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut matching = Self::with_hasher(self.hash_builder.clone());
+        let mut rest = Self::with_hasher(self.hash_builder.clone());
+        for (key, value) in self.iter() {
+            let out = if f(key, value) {
+                &mut matching
+            } else {
+                &mut rest
+            };
+            out.insert(key.clone(), value.clone());
+        }
+        (matching, rest)
+    }
+
+    /// Like [`IndexMap::retain`], but lets `f` modify the values it keeps.
+    pub fn retain_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        for entry in self.entries.iter_mut() {
+            if let Some(bucket) = entry {
+                if !f(&bucket.key, &mut bucket.value) {
+                    *entry = None;
+                }
+            }
+        }
+        self.entries.retain(Option::is_some);
+        self.reindex();
+    }
+
+    /// Drops the empty slots left behind by removals, keeping the order of
+    /// the remaining entries. Does nothing if there are no such slots.
+    pub fn compact(&mut self) {
+        if self.len != self.entries.len() {
+            self.entries.retain(Option::is_some);
+            self.reindex();
+        }
+    }
+
+    /// Like [`IndexMap::compact`], but also resets the capacity hint to the
+    /// number of live entries.
+    pub fn shrink_to_fit(&mut self) {
+        self.compact();
+        self.capacity = self.len;
+    }
+
+    /// Like [`IndexMap::shrink_to_fit`], but keeps the capacity hint at no
+    /// less than `min_capacity`. A hint that is already lower is left as is.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.compact();
+        self.capacity = self.capacity.min(self.len.max(min_capacity));
+    }
+
+    /// Consumes the map, returning its entries sorted by key.
+    pub fn into_sorted_vec(self) -> Vec<(K, V)>
+    where
+        K: Ord,
+    {
+        let mut entries: Vec<_> = self.into_iter().collect();
+        entries.sort_unstable_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        entries
+    }
+
+    /// Consumes the map, returning an iterator over its entries sorted by
+    /// key. The entries are sorted up front, as in
+    /// [`IndexMap::into_sorted_vec`].
+    pub fn into_sorted_iter(self) -> std::vec::IntoIter<(K, V)>
+    where
+        K: Ord,
+    {
+        self.into_sorted_vec().into_iter()
+    }
+
+    /// Consumes the map, returning a new one with each key replaced by `f`
+    /// applied to it, in the same order. Every new key is rehashed.
+    ///
+    /// If several keys map to the same new key, the entry stays at the
+    /// position of the first of them and holds the value of the last, as
+    /// with [`IndexMap::insert`].
+    pub fn map_keys<L, F>(self, mut f: F) -> IndexMap<L, V, S, P>
+    where
+        L: Clone + Hash + Eq,
+        F: FnMut(K) -> L,
+    {
+        let mut out = IndexMap::with_capacity_and_hasher(self.capacity, self.hash_builder.clone());
+        for (key, value) in self {
+            out.insert(f(key), value);
+        }
+        out
+    }
+
+    pub fn sort_keys(&mut self)
+    where
+        K: Ord,
+    {
+        self.sort_by(|k1, _, k2, _| k1.cmp(k2));
+    }
+
+    /// Sorts the entries with the comparator `cmp`. The sort is stable, and
+    /// empty slots left behind by removals are dropped in the process.
+    pub fn sort_by<F>(&mut self, mut cmp: F)
+    where
+        F: FnMut(&K, &V, &K, &V) -> Ordering,
+    {
+        let mut buckets: Vec<_> = std::mem::take(&mut self.entries)
+            .into_iter()
+            .flatten()
+            .collect();
+        buckets.sort_by(|a, b| cmp(&a.key, &a.value, &b.key, &b.value));
+        self.entries = buckets.into_iter().map(Some).collect();
+        self.reindex();
+    }
+
+    /// Store `bucket` as a live entry for a key that is not in the map,
+    /// returning its slot. Only one entry is kept per hash, so if another key
+    /// has the same hash its entry is replaced in place, as
+    /// [`IndexMap::insert`] does; otherwise `bucket` is appended.
+    fn put(&mut self, bucket: Bucket<K, V>) -> usize {
+        match self.indices.get(&bucket.hash) {
+            Some(&slot) => {
+                self.entries.set(slot, Some(bucket));
+                slot
+            }
+            None => self.push(bucket),
+        }
+    }
+
+    /// Append `bucket` as a new live entry, returning its slot.
+    fn push(&mut self, bucket: Bucket<K, V>) -> usize {
+        let idx = self.entries.len();
+        self.indices.insert(bucket.hash, idx);
+        self.entries.push_back(Some(bucket));
+        self.len = grow_len(self.len);
+        idx
+    }
+
+    /// Removes every entry whose value `same` considers equal to the value of
+    /// the previous surviving entry, so that only the first entry of each
+    /// run is kept. `same` is called with the previous value first.
+    pub fn dedup_values_by<F>(&mut self, mut same: F)
+    where
+        F: FnMut(&V, &V) -> bool,
+    {
+        let mut buckets: Vec<_> = std::mem::take(&mut self.entries)
+            .into_iter()
+            .flatten()
+            .collect();
+        buckets.dedup_by(|current, previous| same(&previous.value, &current.value));
+        self.entries = buckets.into_iter().map(Some).collect();
+        self.reindex();
+    }
+
+    /// Split the live bucket at `slot` off a copy of this map.
+    fn split_slot(&self, slot: usize) -> ((&K, &V), Self) {
+        let bucket = self.entries[slot].as_ref().unwrap();
+        let mut rest = self.clone();
+        rest.entries.set(slot, None);
+        rest.indices.remove(&bucket.hash);
+        rest.len -= 1;
+        ((&bucket.key, &bucket.value), rest)
+    }
+
+    /// Remove the live bucket at `slot`, shifting the following slots down.
+    fn shift_remove_slot(&mut self, slot: usize) -> Bucket<K, V> {
+        let bucket = self.entries.remove(slot).unwrap();
+        self.indices.remove(&bucket.hash);
+        self.len -= 1;
+        self.reindex_from(slot);
+        bucket
+    }
+
+    /// Remove the live bucket at `slot`, moving the last live bucket into it.
+    fn swap_remove_slot(&mut self, slot: usize) -> Bucket<K, V> {
+        self.trim_back();
+
+        let last = self.entries.pop_back().flatten().unwrap();
+        let bucket = if slot == self.entries.len() {
+            last
+        } else {
+            self.indices.insert(last.hash, slot);
+            self.entries.set(slot, Some(last)).unwrap()
+        };
+
+        self.indices.remove(&bucket.hash);
+        self.len -= 1;
+        bucket
+    }
+
+    /// Drop the empty slots at the end of `entries`.
+    fn trim_back(&mut self) {
+        while let Some(None) = self.entries.back() {
+            self.entries.pop_back();
+        }
+    }
+
+    /// Rebuild `indices` so that every live bucket points at its current slot.
+    fn reindex(&mut self) {
+        self.indices.clear();
+        self.reindex_from(0);
+        self.len = self.indices.len();
+    }
+
+    /// Point `indices` at the current slot of every live bucket from `start`
+    /// onwards.
+    fn reindex_from(&mut self, start: usize) {
+        for (idx, entry) in self.entries.iter().enumerate().skip(start) {
+            if let Some(bucket) = entry {
+                self.indices.insert(bucket.hash, idx);
+            }
+        }
+    }
+}
+
+/// A difference between two maps, yielded by [`IndexMap::diff`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum Change<'a, K, V> {
+    /// The key is only in the second map.
+    Added { key: &'a K, value: &'a V },
+    /// The key is only in the first map.
+    Removed { key: &'a K, value: &'a V },
+    /// The key is in both maps, with different values.
+    Updated { key: &'a K, old: &'a V, new: &'a V },
+}
+
+/// Returns the length of a map of `len` entries once one more is added.
+///
+/// # Panics
+///
+/// Panics if the length overflows `usize`. A map cannot actually hold that
+/// many entries, but the length is checked rather than left to wrap.
+fn grow_len(len: usize) -> usize {
+    len.checked_add(1).expect("IndexMap: length overflow")
+}
+
+/// Resolves `range` to a start and an end position for a map of `len`
+/// entries. The bounds are not checked against `len`, and bounds that cannot
+/// be made exclusive without overflowing saturate at `usize::MAX`, which is
+/// out of bounds for any map.
+fn range_bounds<R>(range: &R, len: usize) -> (usize, usize)
+where
+    R: RangeBounds<usize>,
+{
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.saturating_add(1),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    (start, end)
+}
+
+/// The error returned by [`IndexMap::try_insert`] when the key is already
+/// present, holding the rejected pair and the entry already in the map.
+///
+/// The entry may belong to a different key with the same hash, which the
+/// insertion would have evicted; `existing_key` then differs from `key`.
+pub struct OccupiedError<'a, K, V> {
+    pub key: K,
+    pub value: V,
+    pub existing_key: &'a K,
+    pub existing: &'a mut V,
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for OccupiedError<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OccupiedError")
+            .field("key", &self.key)
+            .field("value", &self.value)
+            .field("existing_key", &self.existing_key)
+            .field("existing", &self.existing)
+            .finish()
+    }
+}
+
+impl<K: fmt::Debug + PartialEq, V: fmt::Debug> fmt::Display for OccupiedError<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.key == *self.existing_key {
+            write!(
+                f,
+                "failed to insert {:?}, key {:?} already exists with value {:?}",
+                self.value, self.key, self.existing,
+            )
+        } else {
+            write!(
+                f,
+                "failed to insert {:?}, key {:?} collides with key {:?} with value {:?}",
+                self.value, self.key, self.existing_key, self.existing,
+            )
+        }
+    }
+}
+
+impl<K: fmt::Debug + PartialEq, V: fmt::Debug> std::error::Error for OccupiedError<'_, K, V> {}
+
+/// The error returned by [`IndexMap::try_from_sorted_iter`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FromSortedError {
+    /// The key at `index` in the input is not strictly greater than the
+    /// previous one.
+    Unsorted { index: usize },
+    /// The key at `index` in the input has the same hash as an earlier one.
+    HashCollision { index: usize },
+}
+
+impl fmt::Display for FromSortedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FromSortedError::Unsorted { index } => {
+                write!(f, "key at position {index} is out of order or duplicated")
+            }
+            FromSortedError::HashCollision { index } => {
+                write!(f, "key at position {index} collides with an earlier key")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FromSortedError {}
+
+/// The error returned by [`IndexMap::replace_key`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenameError {
+    /// The key to rename is not in the map.
+    NotFound,
+    /// The new key is already used by another entry.
+    AlreadyExists,
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenameError::NotFound => f.write_str("key to rename not found"),
+            RenameError::AlreadyExists => f.write_str("new key already exists"),
+        }
+    }
+}
+
+impl std::error::Error for RenameError {}
+
+/// The error returned by [`IndexMap::get_index_checked`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexError {
+    /// The position is not below the number of live entries.
+    OutOfRange { index: usize, len: usize },
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexError::OutOfRange { index, len } => {
+                write!(f, "index {index} out of range for length {len}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for IndexError {}
+
+/// The error returned by [`IndexMap::get_checked`] when the key is not in
+/// the map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyError;
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("key not found")
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type IndexMap<K, V> = super::IndexMap<K, V, RandomState>;
+
+    #[test]
+    fn new_map_is_empty() {
+        let map: IndexMap<i32, String> = IndexMap::new();
+        assert!(map.iter().next().is_none());
+    }
+
+    #[test]
+    fn get_nonexistent_key() {
+        let map: IndexMap<i32, String> = IndexMap::new();
+        assert_eq!(map.get(&1), None);
+    }
+
+    #[test]
+    fn update_empty_map() {
+        let map: IndexMap<i32, String> = IndexMap::new();
+        let updated = map.update(1, "one".to_string());
+
+        assert_eq!(updated.get(&1), Some(&"one".to_string()));
+        assert!(map.get(&1).is_none()); // Original map remains unchanged
+    }
+
+    #[test]
+    fn update_existing_key() {
+        let map: IndexMap<i32, String> = IndexMap::new();
+        let map = map.update(1, "one".to_string());
+        let updated = map.update(1, "new one".to_string());
+
+        assert_eq!(updated.get(&1), Some(&"new one".to_string()));
+        assert_eq!(map.get(&1), Some(&"one".to_string())); // Original map remains unchanged
+    }
+
+    #[test]
+    fn multiple_updates() {
+        let map: IndexMap<i32, String> = IndexMap::new();
+        let map = map.update(1, "one".to_string());
+        let map = map.update(2, "two".to_string());
+        let map = map.update(3, "three".to_string());
+
+        assert_eq!(map.get(&1), Some(&"one".to_string()));
+        assert_eq!(map.get(&2), Some(&"two".to_string()));
+        assert_eq!(map.get(&3), Some(&"three".to_string()));
+    }
+
+    #[test]
+    fn insert_empty_map() {
+        let mut map: IndexMap<i32, String> = IndexMap::new();
+        map.insert(1, "one".to_string());
+
+        assert_eq!(map.get(&1), Some(&"one".to_string()));
+    }
+
+    #[test]
+    fn insert_existing_key() {
+        let mut map: IndexMap<i32, String> = IndexMap::new();
+        map.insert(1, "one".to_string());
+        map.insert(1, "new one".to_string());
+
+        assert_eq!(map.get(&1), Some(&"new one".to_string()));
+    }
+
+    #[test]
+    fn multiple_inserts() {
+        let mut map: IndexMap<i32, String> = IndexMap::new();
+        map.insert(1, "one".to_string());
+        map.insert(2, "two".to_string());
+        map.insert(3, "three".to_string());
+
+        assert_eq!(map.get(&1), Some(&"one".to_string()));
+        assert_eq!(map.get(&2), Some(&"two".to_string()));
+        assert_eq!(map.get(&3), Some(&"three".to_string()));
+    }
+
+    #[test]
+    fn iter_order() {
+        let map: IndexMap<i32, String> = IndexMap::new();
+        let map = map
+            .update(1, "one".to_string())
+            .update(2, "two".to_string())
+            .update(3, "three".to_string());
+
+        let items: Vec<_> = map.iter().collect();
+        assert_eq!(items.len(), 3);
+
+        // Assuming insertion order is maintained
+        assert_eq!(items[0], (&1, &"one".to_string()));
+        assert_eq!(items[1], (&2, &"two".to_string()));
+        assert_eq!(items[2], (&3, &"three".to_string()));
+    }
+
+    #[test]
+    fn with_string_keys() {
+        let map: IndexMap<String, i32> = IndexMap::new();
+        let map = map
+            .update("one".to_string(), 1)
+            .update("two".to_string(), 2);
+
+        assert_eq!(map.get(&"one".to_string()), Some(&1));
+        assert_eq!(map.get(&"two".to_string()), Some(&2));
+    }
+
+    #[test]
+    fn update_preserves_other_values() {
+        let map: IndexMap<i32, String> = IndexMap::new();
+        let map = map
+            .update(1, "one".to_string())
+            .update(2, "two".to_string());
+        let updated = map.update(1, "new one".to_string());
+
+        assert_eq!(updated.get(&1), Some(&"new one".to_string()));
+        assert_eq!(updated.get(&2), Some(&"two".to_string())); // Other values preserved
+    }
+
+    #[test]
+    fn update_complex_type() {
+        #[derive(Clone, Hash, Eq, PartialEq)]
+        struct ComplexKey {
+            id: i32,
+            name: String,
+        }
+
+        let map: IndexMap<ComplexKey, Vec<i32>> = IndexMap::new();
+        let key = ComplexKey {
+            id: 1,
+            name: "test".to_string(),
+        };
+        let map = map.update(key.clone(), vec![1, 2, 3]);
+
+        assert_eq!(map.get(&key), Some(&vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn update_zero_sized_types() {
+        #[derive(Clone, Hash, Eq, PartialEq)]
+        struct Empty;
+
+        let map: IndexMap<Empty, Empty> = IndexMap::new();
+        let map = map.update(Empty, Empty);
+
+        assert!(map.get(&Empty).is_some());
+    }
+
+    #[test]
+    fn test_without_from_empty_map() {
+        let map: IndexMap<i32, String> = IndexMap::new();
+        let result = map.without(&1);
+        assert!(result.get(&1).is_none());
+        assert_eq!(result.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_without_existing_key() {
+        let map = IndexMap::new()
+            .update(1, "one".to_string())
+            .update(2, "two".to_string());
+
+        let result = map.without(&1);
+
+        assert!(result.get(&1).is_none());
+        assert_eq!(result.get(&2), Some(&"two".to_string()));
+        assert_eq!(result.iter().count(), 1);
+
+        // Original map should remain unchanged
+        assert_eq!(map.get(&1), Some(&"one".to_string()));
+        assert_eq!(map.get(&2), Some(&"two".to_string()));
+    }
+
+    #[test]
+    fn test_without_nonexistent_key() {
+        let map = IndexMap::new().update(1, "one".to_string());
+
+        let result = map.without(&2);
+
+        assert_eq!(result.get(&1), Some(&"one".to_string()));
+        assert_eq!(result.iter().count(), 1);
+    }
+
+    #[test]
+    fn test_without_preserves_order() {
+        let map = IndexMap::new()
+            .update(1, "one".to_string())
+            .update(2, "two".to_string())
+            .update(3, "three".to_string())
+            .update(4, "four".to_string());
+
+        let result = map.without(&2);
+
+        let items: Vec<_> = result.iter().collect();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0], (&1, &"one".to_string()));
+        assert_eq!(items[1], (&3, &"three".to_string()));
+        assert_eq!(items[2], (&4, &"four".to_string()));
+    }
+
+    #[test]
+    fn test_without_chaining() {
+        let map = IndexMap::new()
+            .update(1, "one".to_string())
+            .update(2, "two".to_string())
+            .update(3, "three".to_string());
+
+        let result = map.without(&1).without(&2);
+
+        assert!(result.get(&1).is_none());
+        assert!(result.get(&2).is_none());
+        assert_eq!(result.get(&3), Some(&"three".to_string()));
+        assert_eq!(result.iter().count(), 1);
+    }
+
+    #[test]
+    fn test_without_all_elements() {
+        let map = IndexMap::new()
+            .update(1, "one".to_string())
+            .update(2, "two".to_string());
+
+        let result = map.without(&1).without(&2);
+
+        assert_eq!(result.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_without_with_string_keys() {
+        let map = IndexMap::new()
+            .update("one".to_string(), 1)
+            .update("two".to_string(), 2);
+
+        let result = map.without(&"one".to_string());
+
+        assert!(result.get(&"one".to_string()).is_none());
+        assert_eq!(result.get(&"two".to_string()), Some(&2));
+    }
+
+    #[test]
+    fn test_without_complex_key() {
+        #[derive(Clone, Hash, Eq, PartialEq, Debug)]
+        struct ComplexKey {
+            id: i32,
+            name: String,
+        }
+
+        let key1 = ComplexKey {
+            id: 1,
+            name: "first".to_string(),
+        };
+        let key2 = ComplexKey {
+            id: 2,
+            name: "second".to_string(),
+        };
+
+        let map = IndexMap::new()
+            .update(key1.clone(), "value1".to_string())
+            .update(key2.clone(), "value2".to_string());
+
+        let result = map.without(&key1);
+
+        assert!(result.get(&key1).is_none());
+        assert_eq!(result.get(&key2), Some(&"value2".to_string()));
+    }
+
+    #[test]
+    fn retain_even_keys() {
+        let mut map: IndexMap<i32, String> = (0..10).map(|i| (i, i.to_string())).collect();
+        map.retain(|k, _| k % 2 == 0);
+
+        assert_eq!(map.len(), 5);
+        let keys: Vec<_> = map.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![0, 2, 4, 6, 8]);
+
+        for i in 0..10 {
+            assert_eq!(map.contains_key(&i), i % 2 == 0);
+        }
+        assert_eq!(map.get(&4), Some(&"4".to_string()));
+    }
+
+    #[test]
+    fn extend_overwrites_in_place() {
+        let mut map: IndexMap<i32, &str> = IndexMap::new();
+        map.insert(1, "one");
+        map.insert(2, "two");
+
+        map.extend(vec![(3, "three"), (1, "uno"), (4, "four"), (3, "tres")]);
+
+        let items: Vec<_> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(
+            items,
+            vec![(1, "uno"), (2, "two"), (3, "tres"), (4, "four")]
+        );
+    }
+
+    #[test]
+    fn extend_from_refs() {
+        let mut map: IndexMap<i32, i32> = IndexMap::new();
+        map.insert(1, 10);
+
+        let pairs = [(2, 20), (1, 11)];
+        map.extend(pairs.iter().map(|(k, v)| (k, v)));
+
+        let items: Vec<_> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(items, vec![(1, 11), (2, 20)]);
+    }
+
+    #[test]
+    fn get_index_skips_removed() {
+        let map: IndexMap<i32, i32> = (0..5).map(|i| (i, i * 10)).collect();
+        let map = map.without(&1);
+
+        assert_eq!(map.get_index(0), Some((&0, &0)));
+        assert_eq!(map.get_index(1), Some((&2, &20)));
+        assert_eq!(map.get_index(3), Some((&4, &40)));
+        assert_eq!(map.get_index(4), None);
+    }
+
+    #[test]
+    fn index_by_key_and_position() {
+        let map: IndexMap<i32, &str> = [(5, "five"), (3, "three"), (8, "eight")]
+            .into_iter()
+            .collect();
+        let map = map.without(&5);
+
+        assert_eq!(map[&3], "three");
+        assert_eq!(map[&8], "eight");
+        assert_eq!(map[0], "three");
+        assert_eq!(map[1], "eight");
+    }
+
+    #[test]
+    #[should_panic(expected = "key not found")]
+    fn index_missing_key_panics() {
+        let map: IndexMap<i32, &str> = [(1, "one")].into_iter().collect();
+        let _ = map[&2];
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn index_out_of_bounds_panics() {
+        let map: IndexMap<i32, &str> = [(1, "one")].into_iter().collect();
+        let _ = map[1];
+    }
+
+    #[test]
+    fn first_and_last() {
+        let map: IndexMap<i32, i32> = IndexMap::new();
+        assert_eq!(map.first(), None);
+        assert_eq!(map.last(), None);
+
+        let map: IndexMap<i32, i32> = (0..5).map(|i| (i, i * 10)).collect();
+        assert_eq!(map.first(), Some((&0, &0)));
+        assert_eq!(map.last(), Some((&4, &40)));
+
+        let map = map.without(&0).without(&4).without(&3);
+        assert_eq!(map.first(), Some((&1, &10)));
+        assert_eq!(map.last(), Some((&2, &20)));
+
+        let map = map.without(&1).without(&2);
+        assert_eq!(map.first(), None);
+        assert_eq!(map.last(), None);
+    }
+
+    #[test]
+    fn iter_rev_skips_removed() {
+        let map: IndexMap<i32, i32> = (0..6).map(|i| (i, i * 10)).collect();
+        let map = map.without(&2).without(&3).without(&5);
+
+        let keys: Vec<_> = map.iter().rev().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![4, 1, 0]);
+
+        let keys: Vec<_> = map.clone().into_iter().rev().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec![4, 1, 0]);
+    }
+
+    #[test]
+    fn iter_meets_in_the_middle() {
+        let map: IndexMap<i32, i32> = (0..6).map(|i| (i, i)).collect();
+        let map = map.without(&2).without(&3);
+
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&0, &0)));
+        assert_eq!(iter.next_back(), Some((&5, &5)));
+        assert_eq!(iter.next_back(), Some((&4, &4)));
+        assert_eq!(iter.next(), Some((&1, &1)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        let mut iter = map.into_iter();
+        assert_eq!(iter.next_back(), Some((5, 5)));
+        assert_eq!(iter.next(), Some((0, 0)));
+        assert_eq!(iter.next(), Some((1, 1)));
+        assert_eq!(iter.next_back(), Some((4, 4)));
+        assert_eq!(iter.next_back(), None);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn len_counts_live_entries() {
+        let map: IndexMap<i32, i32> = (0..6).map(|i| (i, i * 10)).collect();
+        let map = map.without(&0).without(&3).without(&42);
+
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.iter().len(), 4);
+        assert_eq!(map.keys().len(), 4);
+        assert_eq!(map.values().len(), 4);
+        assert_eq!(map.clone().into_iter().len(), 4);
+
+        let mut iter = map.iter();
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.len(), 2);
+
+        let keys: Vec<_> = map.keys().copied().collect();
+        assert_eq!(keys, vec![1, 2, 4, 5]);
+        let values: Vec<_> = map.values().copied().collect();
+        assert_eq!(values, vec![10, 20, 40, 50]);
+
+        let map = map.without(&1).without(&2).without(&4).without(&5);
+        assert!(map.is_empty());
+        assert_eq!(map.iter().len(), 0);
+    }
+
+    #[test]
+    fn with_capacity_large_build() {
+        let mut map: IndexMap<i32, i32> = IndexMap::with_capacity(1000);
+        assert!(map.is_empty());
+        assert_eq!(map.capacity(), 1000);
+
+        for i in 0..2000 {
+            map.insert(i, i * 2);
+        }
+
+        assert_eq!(map.len(), 2000);
+        assert!(map.capacity() >= 2000);
+        assert_eq!(map.get(&1999), Some(&3998));
+
+        let map: IndexMap<i32, i32> = IndexMap::with_capacity_and_hasher(16, RandomState::new());
+        assert_eq!(map.capacity(), 16);
+    }
+
+    fn keys<K: Copy, V>(map: &IndexMap<K, V>) -> Vec<K> {
+        map.keys().copied().collect()
+    }
+
+    #[test]
+    fn remove_keeps_positions() {
+        let mut map: IndexMap<i32, i32> = (0..5).map(|i| (i, i * 10)).collect();
+
+        assert_eq!(map.remove(&1), Some(10));
+        assert_eq!(map.remove(&1), None);
+        assert_eq!(keys(&map), vec![0, 2, 3, 4]);
+        assert_eq!(map.len(), 4);
+        for i in [0, 2, 3, 4] {
+            assert_eq!(map.get(&i), Some(&(i * 10)));
+        }
+    }
+
+    #[test]
+    fn shift_remove_preserves_order() {
+        let mut map: IndexMap<i32, i32> = (0..5).map(|i| (i, i * 10)).collect();
+
+        assert_eq!(map.shift_remove(&1), Some(10));
+        assert_eq!(map.shift_remove(&1), None);
+        assert_eq!(keys(&map), vec![0, 2, 3, 4]);
+        assert_eq!(map.get_index(1), Some((&2, &20)));
+        for i in [0, 2, 3, 4] {
+            assert_eq!(map.get(&i), Some(&(i * 10)));
+        }
+
+        map.insert(5, 50);
+        assert_eq!(keys(&map), vec![0, 2, 3, 4, 5]);
+        assert_eq!(map.get(&5), Some(&50));
+    }
+
+    #[test]
+    fn swap_remove_moves_last() {
+        let mut map: IndexMap<i32, i32> = (0..5).map(|i| (i, i * 10)).collect();
+
+        assert_eq!(map.swap_remove(&1), Some(10));
+        assert_eq!(map.swap_remove(&1), None);
+        assert_eq!(keys(&map), vec![0, 4, 2, 3]);
+        for i in [0, 2, 3, 4] {
+            assert_eq!(map.get(&i), Some(&(i * 10)));
+        }
+
+        assert_eq!(map.swap_remove(&3), Some(30));
+        assert_eq!(keys(&map), vec![0, 4, 2]);
+
+        map.insert(5, 50);
+        assert_eq!(keys(&map), vec![0, 4, 2, 5]);
+        assert_eq!(map.get(&5), Some(&50));
+    }
+
+    #[test]
+    fn swap_remove_skips_trailing_removed() {
+        let mut map: IndexMap<i32, i32> = (0..5).map(|i| (i, i * 10)).collect();
+        map.remove(&4);
+        map.remove(&3);
+
+        assert_eq!(map.swap_remove(&0), Some(0));
+        assert_eq!(keys(&map), vec![2, 1]);
+        assert_eq!(map.get(&2), Some(&20));
+        assert_eq!(map.get(&1), Some(&10));
+    }
+
+    #[test]
+    fn shift_and_swap_without() {
+        let map: IndexMap<i32, i32> = (0..4).map(|i| (i, i)).collect();
+
+        assert_eq!(keys(&map.shift_without(&0)), vec![1, 2, 3]);
+        assert_eq!(keys(&map.swap_without(&0)), vec![3, 1, 2]);
+        assert_eq!(keys(&map.swap_without(&9)), vec![0, 1, 2, 3]);
+        assert_eq!(keys(&map), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn sort_keys_reorders_and_resolves() {
+        let mut map: IndexMap<i32, String> = [5, 3, 9, 1, 7]
+            .into_iter()
+            .map(|i| (i, i.to_string()))
+            .collect();
+        map.remove(&9);
+        map.sort_keys();
+
+        assert_eq!(keys(&map), vec![1, 3, 5, 7]);
+        assert_eq!(map.len(), 4);
+        for i in [1, 3, 5, 7] {
+            assert_eq!(map.get(&i), Some(&i.to_string()));
+        }
+        assert_eq!(map.get(&9), None);
+
+        map.insert(2, "2".to_string());
+        assert_eq!(keys(&map), vec![1, 3, 5, 7, 2]);
+    }
+
+    #[test]
+    fn sort_by_value_descending() {
+        let mut map: IndexMap<&str, i32> = [("a", 2), ("b", 3), ("c", 1), ("d", 3)]
+            .into_iter()
+            .collect();
+        map.sort_by(|_, v1, _, v2| v2.cmp(v1));
+
+        assert_eq!(keys(&map), vec!["b", "d", "a", "c"]);
+        assert_eq!(map.get(&"c"), Some(&1));
+    }
+
+    #[test]
+    fn for_loop_over_borrowed_map() {
+        let map: IndexMap<i32, i32> = (0..4).map(|i| (i, i * 10)).collect();
+        let map = map.without(&2);
+
+        let mut seen = Vec::new();
+        for (k, v) in &map {
+            seen.push((*k, *v));
+        }
+        assert_eq!(seen, vec![(0, 0), (1, 10), (3, 30)]);
+    }
+
+    #[test]
+    fn get_key_value_returns_stored_key() {
+        #[derive(Clone, Debug)]
+        struct Tagged {
+            id: i32,
+            tag: &'static str,
+        }
+
+        impl PartialEq for Tagged {
+            fn eq(&self, other: &Self) -> bool {
+                self.id == other.id
+            }
+        }
+
+        impl Eq for Tagged {}
+
+        impl Hash for Tagged {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.id.hash(state);
+            }
+        }
+
+        let mut map: IndexMap<Tagged, i32> = IndexMap::new();
+        map.insert(
+            Tagged {
+                id: 1,
+                tag: "stored",
+            },
+            10,
+        );
+
+        let probe = Tagged {
+            id: 1,
+            tag: "probe",
+        };
+        let (key, value) = map.get_key_value(&probe).unwrap();
+        assert_eq!(key.tag, "stored");
+        assert_eq!(*value, 10);
+
+        assert!(map
+            .get_key_value(&Tagged {
+                id: 2,
+                tag: "stored"
+            })
+            .is_none());
+    }
+
+    #[test]
+    fn arc_map_across_threads() {
+        let map: ArcIndexMap<i32, String> = (0..100).map(|i| (i, i.to_string())).collect();
+        let map = map.without(&50);
+
+        let snapshot = map.clone();
+        let handle = std::thread::spawn(move || {
+            assert_eq!(snapshot.get(&42), Some(&"42".to_string()));
+            snapshot.update(100, "100".to_string())
+        });
+        let updated = handle.join().unwrap();
+
+        assert_eq!(updated.len(), 100);
+        assert_eq!(updated.get(&100), Some(&"100".to_string()));
+        assert_eq!(map.len(), 99);
+        assert_eq!(map.get(&100), None);
+    }
+
+    #[test]
+    fn merge_with_disjoint() {
+        let a: IndexMap<i32, i32> = [(1, 10), (2, 20)].into_iter().collect();
+        let b: IndexMap<i32, i32> = [(4, 40), (3, 30)].into_iter().collect();
+
+        let merged = a.merge_with(&b, |_, x, y| x + y);
+        let items: Vec<_> = merged.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(items, vec![(1, 10), (2, 20), (4, 40), (3, 30)]);
+    }
+
+    #[test]
+    fn merge_with_overlapping() {
+        let a: IndexMap<i32, i32> = [(1, 10), (2, 20), (3, 30)].into_iter().collect();
+        let b: IndexMap<i32, i32> = [(4, 40), (2, 2), (1, 1)].into_iter().collect();
+
+        let merged = a.merge_with(&b, |k, x, y| k * 1000 + x + y);
+        let items: Vec<_> = merged.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(items, vec![(1, 1011), (2, 2022), (3, 30), (4, 40)]);
+
+        // Neither input is modified
+        assert_eq!(a.get(&1), Some(&10));
+        assert_eq!(b.get(&1), Some(&1));
+        assert_eq!(a.len(), 3);
+    }
+
+    #[test]
+    fn merge_with_empty() {
+        let a: IndexMap<i32, i32> = [(1, 10), (2, 20)].into_iter().collect();
+        let empty: IndexMap<i32, i32> = IndexMap::new();
+
+        assert_eq!(keys(&a.merge_with(&empty, |_, x, _| *x)), vec![1, 2]);
+        assert_eq!(keys(&empty.merge_with(&a, |_, x, _| *x)), vec![1, 2]);
+        assert!(empty.merge_with(&empty, |_, x, _| *x).is_empty());
+    }
+
+    #[test]
+    fn truncate_to_zero() {
+        let mut map: IndexMap<i32, i32> = (0..5).map(|i| (i, i)).collect();
+        map.truncate(0);
+
+        assert!(map.is_empty());
+        assert_eq!(map.get(&0), None);
+        map.insert(7, 7);
+        assert_eq!(keys(&map), vec![7]);
+    }
+
+    #[test]
+    fn truncate_to_middle() {
+        let mut map: IndexMap<i32, i32> = (0..8).map(|i| (i, i * 10)).collect();
+        map.remove(&1);
+        map.remove(&6);
+        map.truncate(3);
+
+        assert_eq!(keys(&map), vec![0, 2, 3]);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(&3), Some(&30));
+        assert_eq!(map.get(&4), None);
+        assert_eq!(map.get(&7), None);
+
+        map.insert(4, 44);
+        assert_eq!(keys(&map), vec![0, 2, 3, 4]);
+        assert_eq!(map.get(&4), Some(&44));
+    }
+
+    #[test]
+    fn truncate_beyond_len() {
+        let mut map: IndexMap<i32, i32> = (0..5).map(|i| (i, i)).collect();
+        map.remove(&4);
+        map.truncate(4);
+        assert_eq!(keys(&map), vec![0, 1, 2, 3]);
+        map.truncate(10);
+        assert_eq!(keys(&map), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn split_off_at_zero() {
+        let mut map: IndexMap<i32, i32> = (0..4).map(|i| (i, i)).collect();
+        let tail = map.split_off(0);
+
+        assert!(map.is_empty());
+        assert_eq!(keys(&tail), vec![0, 1, 2, 3]);
+        assert_eq!(tail.get(&2), Some(&2));
+    }
+
+    #[test]
+    fn split_off_in_middle() {
+        let mut map: IndexMap<i32, i32> = (0..8).map(|i| (i, i * 10)).collect();
+        map.remove(&1);
+        map.remove(&5);
+        let tail = map.split_off(3);
+
+        assert_eq!(keys(&map), vec![0, 2, 3]);
+        assert_eq!(keys(&tail), vec![4, 6, 7]);
+        assert_eq!(tail.len(), 3);
+        for i in [0, 2, 3] {
+            assert_eq!(map.get(&i), Some(&(i * 10)));
+            assert_eq!(tail.get(&i), None);
+        }
+        for i in [4, 6, 7] {
+            assert_eq!(tail.get(&i), Some(&(i * 10)));
+            assert_eq!(map.get(&i), None);
+        }
+        assert_eq!(tail.get_index(0), Some((&4, &40)));
+
+        map.insert(9, 90);
+        assert_eq!(keys(&map), vec![0, 2, 3, 9]);
+    }
+
+    #[test]
+    fn split_off_at_len() {
+        let mut map: IndexMap<i32, i32> = (0..4).map(|i| (i, i)).collect();
+        let tail = map.split_off(4);
+
+        assert!(tail.is_empty());
+        assert_eq!(keys(&map), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn split_off_past_len_panics() {
+        let mut map: IndexMap<i32, i32> = (0..4).map(|i| (i, i)).collect();
+        map.split_off(5);
+    }
+
+    #[test]
+    fn move_index_forward_and_back() {
+        let mut map: IndexMap<i32, i32> = (0..6).map(|i| (i, i * 10)).collect();
+        map.remove(&2);
+
+        map.move_index(0, 3);
+        assert_eq!(keys(&map), vec![1, 3, 4, 0, 5]);
+        map.move_index(4, 1);
+        assert_eq!(keys(&map), vec![1, 5, 3, 4, 0]);
+        map.move_index(2, 2);
+        assert_eq!(keys(&map), vec![1, 5, 3, 4, 0]);
+
+        for i in [0, 1, 3, 4, 5] {
+            assert_eq!(map.get(&i), Some(&(i * 10)));
+        }
+        assert_eq!(map.len(), 5);
+    }
+
+    #[test]
+    fn swap_indices_keeps_lookups() {
+        let mut map: IndexMap<i32, i32> = (0..5).map(|i| (i, i * 10)).collect();
+        map.remove(&1);
+
+        map.swap_indices(0, 3);
+        assert_eq!(keys(&map), vec![4, 2, 3, 0]);
+        map.swap_indices(1, 1);
+        assert_eq!(keys(&map), vec![4, 2, 3, 0]);
+
+        for i in [0, 2, 3, 4] {
+            assert_eq!(map.get(&i), Some(&(i * 10)));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn move_index_out_of_bounds_panics() {
+        let mut map: IndexMap<i32, i32> = (0..3).map(|i| (i, i)).collect();
+        map.move_index(0, 3);
+    }
+
+    #[test]
+    fn pop_from_full_map() {
+        let mut map: IndexMap<i32, i32> = (0..3).map(|i| (i, i * 10)).collect();
+
+        assert_eq!(map.pop(), Some((2, 20)));
+        assert_eq!(map.pop(), Some((1, 10)));
+        assert_eq!(keys(&map), vec![0]);
+        assert_eq!(map.get(&1), None);
+
+        map.insert(5, 50);
+        assert_eq!(keys(&map), vec![0, 5]);
+        assert_eq!(map.get(&5), Some(&50));
+    }
+
+    #[test]
+    fn pop_skips_trailing_removed() {
+        let map: IndexMap<i32, i32> = (0..5).map(|i| (i, i * 10)).collect();
+        let mut map = map.without(&4).without(&3);
+
+        assert_eq!(map.pop(), Some((2, 20)));
+        assert_eq!(map.len(), 2);
+        assert_eq!(keys(&map), vec![0, 1]);
+    }
+
+    #[test]
+    fn pop_from_empty_map() {
+        let mut map: IndexMap<i32, i32> = IndexMap::new();
+        assert_eq!(map.pop(), None);
+
+        let mut map = map.update(1, 1).without(&1);
+        assert_eq!(map.pop(), None);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn hash_matches_eq() {
+        use std::collections::HashSet;
+
+        let a: IndexMap<i32, i32> = [(1, 10), (2, 20), (3, 30)].into_iter().collect();
+        let b = a.update(4, 40).without(&4);
+        let c: IndexMap<i32, i32> = [(3, 30), (1, 10), (2, 20)].into_iter().collect();
+        let d = a.update(3, 31);
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, d);
+
+        let state = RandomState::new();
+        assert_eq!(state.hash_one(&a), state.hash_one(&b));
+        assert_ne!(state.hash_one(&a), state.hash_one(&c));
+        assert_ne!(state.hash_one(&a), state.hash_one(&d));
+
+        let set: HashSet<_> = [a, b, c, d].into_iter().collect();
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn eq_is_order_sensitive() {
+        let a: IndexMap<&str, i32> = [("x", 1), ("y", 2)].into_iter().collect();
+        let b: IndexMap<&str, i32> = [("y", 2), ("x", 1)].into_iter().collect();
+
+        assert_ne!(a, b);
+        assert_eq!(a.get(&"x"), b.get(&"x"));
+        assert_eq!(a.get(&"y"), b.get(&"y"));
+
+        let mut sorted = b.clone();
+        sorted.sort_keys();
+        assert_eq!(a, sorted);
+    }
+
+    #[test]
+    fn eq_ignores_removed_entries() {
+        let a: IndexMap<i32, i32> = [(1, 1), (2, 2), (3, 3)].into_iter().collect();
+        let b: IndexMap<i32, i32> = [(0, 0), (1, 1), (2, 2), (3, 3)].into_iter().collect();
+
+        assert_eq!(a, b.without(&0));
+    }
+
+    #[test]
+    fn reserve_grows_capacity() {
+        let mut map: IndexMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+        assert_eq!(map.capacity(), 10);
+
+        map.reserve(100);
+        assert!(map.capacity() >= 110);
+
+        map.reserve(5);
+        assert!(map.capacity() >= 110);
+    }
+
+    #[test]
+    fn shrink_to_fit_drops_removed_slots() {
+        let mut map: IndexMap<i32, i32> = (0..10).map(|i| (i, i * 10)).collect();
+        map.reserve(100);
+        for key in [0, 3, 4, 9] {
+            map.remove(&key);
+        }
+        assert_eq!(map.entries.len(), 10);
+
+        map.shrink_to_fit();
+        assert_eq!(map.entries.len(), 6);
+        assert_eq!(map.capacity(), 6);
+        assert_eq!(keys(&map), vec![1, 2, 5, 6, 7, 8]);
+        assert_eq!(map.get(&7), Some(&70));
+        assert_eq!(map.get(&3), None);
+        assert_eq!(map.get_index(2), Some((&5, &50)));
+    }
+
+    #[test]
+    fn get_index_of_accounts_for_removals() {
+        let map: IndexMap<&str, i32> = [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+        assert_eq!(map.get_index_of(&"a"), Some(0));
+        assert_eq!(map.get_index_of(&"c"), Some(2));
+        assert_eq!(map.get_index_of(&"z"), None);
+
+        let map = map.without(&"a");
+        assert_eq!(map.get_index_of(&"a"), None);
+        assert_eq!(map.get_index_of(&"b"), Some(0));
+        assert_eq!(map.get_index_of(&"c"), Some(1));
+
+        for key in ["b", "c"] {
+            let index = map.get_index_of(&key).unwrap();
+            assert_eq!(map.get_index(index).map(|(k, _)| *k), Some(key));
+        }
+    }
+
+    #[test]
+    fn get_full() {
+        let map: IndexMap<&str, i32> = [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+        let map = map.without(&"a");
+
+        assert_eq!(map.get_full(&"c"), Some((1, &"c", &3)));
+        assert_eq!(map.get_full(&"a"), None);
+    }
+
+    #[test]
+    fn insert_returns_previous_value() {
+        let mut map: IndexMap<&str, i32> = IndexMap::new();
+        assert_eq!(map.insert("a", 1), None);
+        assert_eq!(map.insert("b", 2), None);
+        assert_eq!(map.insert("a", 3), Some(1));
+        assert_eq!(keys(&map), vec!["a", "b"]);
+        assert_eq!(map.get(&"a"), Some(&3));
+    }
+
+    #[test]
+    fn clear_and_reuse() {
+        let mut map: IndexMap<i32, i32> = IndexMap::with_capacity(64);
+        map.extend((0..32).map(|i| (i, i)));
+        map.remove(&5);
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.len(), 0);
+        assert_eq!(map.iter().next(), None);
+        assert_eq!(map.get(&1), None);
+        assert_eq!(map.capacity(), 64);
+
+        map.insert(5, 50);
+        map.insert(1, 10);
+        assert_eq!(keys(&map), vec![5, 1]);
+        assert_eq!(map.get(&5), Some(&50));
+        assert_eq!(map.get_index_of(&1), Some(1));
+    }
+
+    #[test]
+    fn drain_empties_map() {
+        let mut map: IndexMap<i32, &str> = [(3, "c"), (1, "a"), (2, "b")].into_iter().collect();
+        map.remove(&1);
+
+        let drained: Vec<_> = map.drain().collect();
+        assert_eq!(drained, vec![(3, "c"), (2, "b")]);
+        assert!(map.is_empty());
+        assert_eq!(map.iter().count(), 0);
+        assert_eq!(map.get(&3), None);
+
+        map.insert(4, "d");
+        map.insert(3, "c");
+        assert_eq!(keys(&map), vec![4, 3]);
+        assert_eq!(map.get(&3), Some(&"c"));
+    }
+
+    #[test]
+    fn append_overlapping() {
+        let mut map: IndexMap<char, i32> = [('a', 1), ('b', 2), ('c', 3)].into_iter().collect();
+        let mut other: IndexMap<char, i32> = [('d', 4), ('b', 20), ('e', 5), ('a', 10)]
+            .into_iter()
+            .collect();
+
+        map.append(&mut other);
+        assert_eq!(keys(&map), vec!['a', 'b', 'c', 'd', 'e']);
+        assert_eq!(map.get(&'a'), Some(&10));
+        assert_eq!(map.get(&'b'), Some(&20));
+        assert!(other.is_empty());
+        assert_eq!(other.get(&'d'), None);
+    }
+
+    #[test]
+    fn append_disjoint() {
+        let mut map: IndexMap<i32, i32> = (0..3).map(|i| (i, i)).collect();
+        let mut other: IndexMap<i32, i32> = (3..6).rev().map(|i| (i, i)).collect();
+        other.remove(&4);
+
+        map.append(&mut other);
+        assert_eq!(keys(&map), vec![0, 1, 2, 5, 3]);
+        assert_eq!(map.len(), 5);
+        assert!(other.is_empty());
+
+        other.insert(7, 7);
+        assert_eq!(keys(&other), vec![7]);
+    }
+
+    #[test]
+    fn drain_dropped_early() {
+        let mut map: IndexMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+
+        let mut drain = map.drain();
+        assert_eq!(drain.len(), 10);
+        assert_eq!(drain.next(), Some((0, 0)));
+        drop(drain);
+
+        assert!(map.is_empty());
+        assert_eq!(map.get_index(0), None);
+    }
+
+    #[test]
+    fn insert_full_reports_position() {
+        let mut map: IndexMap<&str, i32> = [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+        map.remove(&"a");
+
+        assert_eq!(map.insert_full("c", 30), (1, Some(3)));
+        assert_eq!(map.insert_full("d", 4), (2, None));
+        assert_eq!(map.get_index(2), Some((&"d", &4)));
+    }
+
+    #[test]
+    fn to_vector_matches_iter() {
+        let map: IndexMap<i32, char> = [(2, 'b'), (1, 'a'), (3, 'c')].into_iter().collect();
+        let map = map.without(&1);
+
+        let vector = map.to_vector();
+        assert_eq!(vector.len(), map.len());
+        assert!(vector.iter().map(|(k, v)| (k, v)).eq(map.iter()));
+        assert_eq!(
+            vector.into_iter().collect::<Vec<_>>(),
+            vec![(2, 'b'), (3, 'c')]
+        );
+    }
+
+    #[test]
+    fn get_range() {
+        let map: IndexMap<i32, i32> = (0..8).map(|i| (i, i)).collect();
+        let range_keys = |iter: Iter<'_, i32, i32, RcK>| iter.map(|(k, _)| *k).collect::<Vec<_>>();
+
+        assert_eq!(range_keys(map.get_range(..)), keys(&map));
+        assert_eq!(range_keys(map.get_range(2..5)), vec![2, 3, 4]);
+        assert_eq!(range_keys(map.get_range(..3)), vec![0, 1, 2]);
+        assert_eq!(range_keys(map.get_range(3..)), vec![3, 4, 5, 6, 7]);
+        assert_eq!(range_keys(map.get_range(6..=7)), vec![6, 7]);
+        assert_eq!(map.get_range(2..5).len(), 3);
+        assert!(map.get_range(2..5).rev().map(|(k, _)| *k).eq([4, 3, 2]));
+    }
+
+    #[test]
+    fn get_range_out_of_bounds() {
+        let map: IndexMap<i32, i32> = (0..8).map(|i| (i, i)).collect();
+
+        assert_eq!(map.get_range(6..100).count(), 2);
+        assert_eq!(map.get_range(8..).count(), 0);
+        assert_eq!(map.get_range(20..30).count(), 0);
+        assert_eq!(map.get_range(4..4).count(), 0);
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = map.get_range(5..2);
+        assert_eq!(reversed.len(), 0);
+        assert_eq!(reversed.count(), 0);
+    }
+
+    #[test]
+    fn get_range_skips_removed_entries() {
+        let mut map: IndexMap<i32, i32> = (0..8).map(|i| (i, i)).collect();
+        map.remove(&0);
+        map.remove(&3);
+        map.remove(&7);
+
+        let range: Vec<_> = map.get_range(1..3).map(|(k, _)| *k).collect();
+        assert_eq!(range, vec![2, 4]);
+        let range: Vec<_> = map.get_range(3..).map(|(k, _)| *k).collect();
+        assert_eq!(range, vec![5, 6]);
+        assert_eq!(map.get_range(3..).len(), 2);
+        assert!(map.get_range(..2).rev().map(|(k, _)| *k).eq([2, 1]));
+    }
+
+    #[test]
+    fn insert_at_new_keys() {
+        let mut map: IndexMap<char, i32> = [('b', 2), ('d', 4)].into_iter().collect();
+
+        assert_eq!(map.insert_at(0, 'a', 1), None);
+        assert_eq!(map.insert_at(2, 'c', 3), None);
+        assert_eq!(map.insert_at(4, 'e', 5), None);
+        assert_eq!(keys(&map), vec!['a', 'b', 'c', 'd', 'e']);
+        for (index, key) in ['a', 'b', 'c', 'd', 'e'].iter().enumerate() {
+            assert_eq!(map.get_index_of(key), Some(index));
+        }
+        assert_eq!(map.get(&'c'), Some(&3));
+    }
+
+    #[test]
+    fn insert_at_moves_existing_key() {
+        let mut map: IndexMap<char, i32> = "abcde".chars().zip(0..).collect();
+
+        assert_eq!(map.insert_at(3, 'a', 10), Some(0));
+        assert_eq!(keys(&map), vec!['b', 'c', 'd', 'a', 'e']);
+        assert_eq!(map.insert_at(0, 'e', 40), Some(4));
+        assert_eq!(keys(&map), vec!['e', 'b', 'c', 'd', 'a']);
+        assert_eq!(map.insert_at(4, 'c', 20), Some(2));
+        assert_eq!(keys(&map), vec!['e', 'b', 'd', 'a', 'c']);
+        assert_eq!(map.len(), 5);
+        assert_eq!(map.get(&'a'), Some(&10));
+        assert_eq!(map.get_index_of(&'c'), Some(4));
+    }
+
+    #[test]
+    fn insert_at_with_removed_entries() {
+        let mut map: IndexMap<char, i32> = "abcd".chars().zip(0..).collect();
+        map.remove(&'a');
+        map.remove(&'d');
+
+        map.insert_at(1, 'x', 9);
+        map.insert_at(3, 'y', 8);
+        assert_eq!(keys(&map), vec!['b', 'x', 'c', 'y']);
+        assert_eq!(map.get_index_of(&'y'), Some(3));
+    }
+
+    #[test]
+    fn insert_at_over_colliding_key() {
+        use std::hash::BuildHasherDefault;
+
+        use crate::hasher::CollidingHasher;
+
+        type CollidingMap = super::IndexMap<u32, &'static str, BuildHasherDefault<CollidingHasher>>;
+
+        let mut map = CollidingMap::default();
+        map.insert(1, "a");
+
+        assert_eq!(map.insert_at(0, 2, "b"), None);
+        assert_eq!(map.len(), 1);
+        assert!(map.iter().eq([(&2, &"b")]));
+        assert_eq!(map.get(&1), None);
+        assert_eq!(map.get_index_of(&2), Some(0));
+
+        assert_eq!(map.insert_at(0, 2, "c"), Some("b"));
+        assert!(map.iter().eq([(&2, &"c")]));
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn insert_at_colliding_out_of_bounds() {
+        use std::hash::BuildHasherDefault;
+
+        use crate::hasher::CollidingHasher;
+
+        type CollidingMap = super::IndexMap<u32, &'static str, BuildHasherDefault<CollidingHasher>>;
+
+        let mut map = CollidingMap::default();
+        map.insert(1, "a");
+        map.insert_at(1, 2, "b");
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn insert_at_out_of_bounds() {
+        let mut map: IndexMap<char, i32> = "ab".chars().zip(0..).collect();
+        map.insert_at(2, 'a', 0);
+    }
+
+    #[test]
+    fn update_many_matches_update() {
+        let base: IndexMap<u32, u32> = (0..100).map(|i| (i, 0)).collect();
+        let pairs: Vec<_> = (0..10_000).map(|i| ((i * 37) % 5000, i)).collect();
+
+        let many = base.update_many(pairs.iter().copied());
+        let folded = pairs
+            .iter()
+            .fold(base.clone(), |map, &(k, v)| map.update(k, v));
+
+        assert_eq!(many, folded);
+        assert_eq!(many.len(), 5000);
+        assert_eq!(base.len(), 100);
+        assert!(base.values().all(|v| *v == 0));
+    }
+
+    #[test]
+    fn update_many_keeps_positions() {
+        let map: IndexMap<char, i32> = [('a', 1), ('b', 2)].into_iter().collect();
+        let map = map.update_many([('c', 3), ('a', 10), ('d', 4), ('c', 30)]);
+
+        assert_eq!(keys(&map), vec!['a', 'b', 'c', 'd']);
+        assert_eq!(map.get(&'a'), Some(&10));
+        assert_eq!(map.get(&'c'), Some(&30));
+    }
+
+    #[test]
+    fn debug_after_removal() {
+        let mut map: IndexMap<&str, i32> = [("c", 3), ("a", 1), ("b", 2)].into_iter().collect();
+        map.remove(&"a");
+
+        let expected = map
+            .iter()
+            .map(|(k, v)| format!("{k:?}: {v:?}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        assert_eq!(format!("{map:?}"), format!("{{{expected}}}"));
+        assert_eq!(format!("{map:?}"), r#"{"c": 3, "b": 2}"#);
+        assert_eq!(format!("{map:#?}"), "{\n    \"c\": 3,\n    \"b\": 2,\n}");
+    }
+
+    #[test]
+    fn try_insert_vacant() {
+        let mut map: IndexMap<&str, i32> = [("a", 1)].into_iter().collect();
+
+        let value = map.try_insert("b", 2).unwrap();
+        assert_eq!(*value, 2);
+        *value += 10;
+
+        assert_eq!(keys(&map), vec!["a", "b"]);
+        assert_eq!(map.get(&"b"), Some(&12));
+    }
+
+    #[test]
+    fn try_insert_occupied() {
+        let mut map: IndexMap<&str, i32> = [("a", 1), ("b", 2)].into_iter().collect();
+
+        let err = map.try_insert("a", 10).unwrap_err();
+        assert_eq!(err.key, "a");
+        assert_eq!(err.value, 10);
+        assert_eq!(*err.existing_key, "a");
+        assert_eq!(*err.existing, 1);
+        assert_eq!(
+            err.to_string(),
+            r#"failed to insert 10, key "a" already exists with value 1"#
+        );
+
+        assert_eq!(map.get(&"a"), Some(&1));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn insert_over_colliding_key() {
+        use std::hash::BuildHasherDefault;
+
+        use crate::hasher::CollidingHasher;
+
+        type CollidingMap = super::IndexMap<u32, &'static str, BuildHasherDefault<CollidingHasher>>;
+
+        let mut map = CollidingMap::default();
+        assert_eq!(map.insert_full(1, "a"), (0, None));
+        assert_eq!(map.insert_full(2, "b"), (0, None));
+        assert_eq!(map.insert(2, "c"), Some("b"));
+
+        let err = map.try_insert(3, "d").unwrap_err();
+        assert_eq!(*err.existing_key, 2);
+        assert_eq!(*err.existing, "c");
+        assert_eq!(
+            err.to_string(),
+            r#"failed to insert "d", key 3 collides with key 2 with value "c""#
+        );
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(&2), Some(&"c"));
+        assert_eq!(map.get(&3), None);
+
+        let err = map.try_insert(2, "e").unwrap_err();
+        assert_eq!(*err.existing_key, 2);
+        assert_eq!(*err.existing, "c");
+    }
+
+    #[test]
+    fn partition() {
+        let map: IndexMap<i32, char> = [(5, 'a'), (2, 'b'), (8, 'c'), (1, 'd'), (4, 'e')]
+            .into_iter()
+            .collect();
+        let map = map.without(&1);
+
+        let (even, odd) = map.partition(|k, _| k % 2 == 0);
+        assert_eq!(keys(&even), vec![2, 8, 4]);
+        assert_eq!(keys(&odd), vec![5]);
+        assert_eq!(even.get(&8), Some(&'c'));
+        assert_eq!(even.len() + odd.len(), map.len());
+        for (key, _) in map.iter() {
+            assert!(even.contains_key(key) != odd.contains_key(key));
+        }
+    }
+
+    #[test]
+    fn retain_mut_decrements_counters() {
+        let mut map: IndexMap<char, u32> = [('a', 2), ('b', 1), ('c', 3), ('d', 1)]
+            .into_iter()
+            .collect();
+        map.remove(&'c');
+
+        map.retain_mut(|_, ttl| {
+            *ttl -= 1;
+            *ttl > 0
+        });
+
+        assert_eq!(keys(&map), vec!['a']);
+        assert_eq!(map.get(&'a'), Some(&1));
+        assert_eq!(map.get(&'b'), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn retain_mut_leaves_clones_untouched() {
+        let original: IndexMap<i32, i32> = (0..6).map(|i| (i, i)).collect();
+        let mut map = original.clone();
+
+        map.retain_mut(|k, v| {
+            *v *= 10;
+            k % 2 == 1
+        });
+
+        assert_eq!(keys(&map), vec![1, 3, 5]);
+        assert_eq!(map.get_index(2), Some((&5, &50)));
+        assert_eq!(original.get(&5), Some(&5));
+        assert_eq!(original.len(), 6);
+    }
+
+    #[test]
+    fn map_values_to_strings() {
+        let map: IndexMap<char, i32> = [('c', 3), ('a', 1), ('b', 2)].into_iter().collect();
+        let map = map.without(&'a');
+
+        let strings = map.map_values(|v| v.to_string());
+        assert_eq!(keys(&strings), vec!['c', 'b']);
+        assert_eq!(strings.get(&'b'), Some(&"2".to_string()));
+        assert_eq!(strings.get(&'a'), None);
+        assert_eq!(strings.get_index(0), Some((&'c', &"3".to_string())));
+        assert_eq!(strings.len(), 2);
+
+        let mut strings = strings;
+        strings.insert('d', "4".to_string());
+        assert_eq!(keys(&strings), vec!['c', 'b', 'd']);
+    }
+
+    #[test]
+    fn map_keys_rehashes() {
+        let map: IndexMap<i32, &str> = [(3, "c"), (1, "a"), (2, "b")].into_iter().collect();
+        let map = map.without(&1);
+
+        let wrapped = map.map_keys(|k| k * 10);
+        assert_eq!(keys(&wrapped), vec![30, 20]);
+        assert_eq!(wrapped.get(&20), Some(&"b"));
+        assert_eq!(wrapped.get(&2), None);
+        assert_eq!(wrapped.len(), 2);
+    }
+
+    #[test]
+    fn map_keys_collapsing() {
+        let map: IndexMap<String, i32> = [("b", 1), ("A", 2), ("B", 3), ("a", 4)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+
+        let lower = map.map_keys(|k| k.to_lowercase());
+        assert_eq!(lower.len(), 2);
+        let pairs: Vec<_> = lower.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(pairs, vec![("b", 3), ("a", 4)]);
+    }
+
+    #[test]
+    fn compact_after_many_removals() {
+        let mut map: IndexMap<u32, u32> = (0..500).map(|i| (i, i * 2)).collect();
+        for key in (0..500).filter(|k| k % 5 != 0) {
+            map = map.without(&key);
+        }
+        assert_eq!(map.len(), 100);
+        assert!(map.entries.len() > 100);
+
+        map.compact();
+        assert_eq!(map.entries.len(), 100);
+        assert_eq!(keys(&map), (0..500).step_by(5).collect::<Vec<_>>());
+        for (index, key) in (0..500).step_by(5).enumerate() {
+            assert_eq!(map.get(&key), Some(&(key * 2)));
+            assert_eq!(map.get_index_of(&key), Some(index));
+        }
+        assert_eq!(map.get(&1), None);
+
+        map.compact();
+        assert_eq!(map.entries.len(), 100);
+    }
+
+    #[test]
+    fn get_many_mut_transfers() {
+        let mut map: IndexMap<&str, i32> = [("a", 10), ("b", 0), ("c", 5), ("d", 1)]
+            .into_iter()
+            .collect();
+        map.remove(&"b");
+
+        let [d, a] = map.get_many_mut([&"d", &"a"]).unwrap();
+        *a -= 4;
+        *d += 4;
+
+        assert_eq!(map.get(&"a"), Some(&6));
+        assert_eq!(map.get(&"d"), Some(&5));
+        assert_eq!(keys(&map), vec!["a", "c", "d"]);
+
+        let [c, d, a] = map.get_many_mut([&"c", &"d", &"a"]).unwrap();
+        assert_eq!((*a, *c, *d), (6, 5, 5));
+    }
+
+    #[test]
+    fn get_many_mut_missing_or_aliased() {
+        let mut map: IndexMap<&str, i32> = [("a", 1), ("b", 2)].into_iter().collect();
+
+        assert!(map.get_many_mut([&"a", &"z"]).is_none());
+        assert!(map.get_many_mut([&"a", &"a"]).is_none());
+        assert!(map.get_many_mut([&"b", &"a", &"b"]).is_none());
+        assert_eq!(map.get_many_mut::<0>([]), Some([]));
+    }
+
+    #[test]
+    fn get_many_mut_does_not_touch_clones() {
+        let original: IndexMap<i32, i32> = (0..200).map(|i| (i, i)).collect();
+        let mut map = original.clone();
+
+        for v in map.get_many_mut([&150, &3, &64]).unwrap() {
+            *v = -1;
+        }
+
+        assert_eq!(map.get(&150), Some(&-1));
+        assert_eq!(map.get(&3), Some(&-1));
+        assert_eq!(map.get(&64), Some(&-1));
+        assert_eq!(map.get(&4), Some(&4));
+        assert_eq!(original.get(&150), Some(&150));
+        assert_eq!(original.get(&3), Some(&3));
+    }
+
+    #[test]
+    fn binary_search_keys() {
+        let mut map: IndexMap<i32, i32> =
+            [50, 10, 40, 20, 30].into_iter().map(|k| (k, k)).collect();
+        map.sort_keys();
+
+        assert_eq!(map.binary_search_keys(&10), Ok(0));
+        assert_eq!(map.binary_search_keys(&30), Ok(2));
+        assert_eq!(map.binary_search_keys(&50), Ok(4));
+        assert_eq!(map.binary_search_keys(&5), Err(0));
+        assert_eq!(map.binary_search_keys(&35), Err(3));
+        assert_eq!(map.binary_search_keys(&60), Err(5));
+    }
+
+    #[test]
+    fn binary_search_keys_with_removed_entries() {
+        let mut map: IndexMap<i32, i32> = (0..10).map(|k| (k * 10, k)).collect();
+        map.remove(&0);
+        map.remove(&40);
+
+        assert_eq!(map.binary_search_keys(&10), Ok(0));
+        assert_eq!(map.binary_search_keys(&50), Ok(3));
+        assert_eq!(map.binary_search_keys(&40), Err(3));
+        assert_eq!(map.binary_search_by(|_, v| v.cmp(&9)), Ok(7));
+
+        let empty: IndexMap<i32, i32> = IndexMap::new();
+        assert_eq!(empty.binary_search_keys(&1), Err(0));
+    }
+
+    #[test]
+    fn iter_from() {
+        let map: IndexMap<char, i32> = "abcde".chars().zip(0..).collect();
+        let map = map.without(&'c');
+        let after = |key| map.iter_from(&key).map(|(k, _)| *k).collect::<String>();
+
+        assert_eq!(after('a'), "bde");
+        assert_eq!(after('b'), "de");
+        assert_eq!(after('e'), "");
+        assert_eq!(after('c'), "abde");
+        assert_eq!(after('z'), "abde");
+        assert_eq!(map.iter_from(&'b').len(), 2);
+    }
+
+    #[test]
+    fn ord_is_lexicographic() {
+        let map = |pairs: &[(i32, i32)]| pairs.iter().copied().collect::<IndexMap<_, _>>();
+
+        assert!(map(&[(1, 1), (2, 2)]) < map(&[(1, 1), (2, 3)]));
+        assert!(map(&[(1, 1), (3, 0)]) > map(&[(1, 1), (2, 9)]));
+        assert!(map(&[(1, 1)]) < map(&[(1, 1), (2, 2)]));
+        assert!(map(&[]) < map(&[(0, 0)]));
+        assert_eq!(
+            map(&[(1, 1), (2, 2)]).cmp(&map(&[(0, 0), (1, 1), (2, 2)]).without(&0)),
+            Ordering::Equal
+        );
+
+        let sorted: std::collections::BTreeSet<_> =
+            [map(&[(2, 0)]), map(&[(1, 5)]), map(&[(1, 0), (2, 0)])]
+                .into_iter()
+                .collect();
+        let firsts: Vec<_> = sorted.iter().map(|m| *m.first().unwrap().0).collect();
+        assert_eq!(firsts, vec![1, 1, 2]);
+        assert_eq!(sorted.first().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn get_by_borrowed_key() {
+        let map: IndexMap<String, i32> = [("abc", 1), ("def", 2)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+
+        assert_eq!(map.get_by("abc"), Some(&1));
+        assert_eq!(map.get_by("def"), map.get(&"def".to_string()));
+        assert_eq!(map.get_by("xyz"), None);
+        assert_eq!(map.without(&"abc".to_string()).get_by("abc"), None);
+
+        let map: IndexMap<i32, char> = [(1, 'a'), (2, 'b')].into_iter().collect();
+        assert_eq!(map.get_by(&2), Some(&'b'));
+        assert_eq!(map.get_by(&3), None);
+    }
+
+    #[test]
+    fn contains_and_without_by_borrowed_key() {
+        let map: IndexMap<String, i32> = ["a", "b", "c"]
+            .into_iter()
+            .map(String::from)
+            .zip(0..)
+            .collect();
+
+        assert!(map.contains_key_by("b"));
+        assert!(!map.contains_key_by("z"));
+
+        let without = map.without_by("b");
+        assert!(!without.contains_key_by("b"));
+        assert_eq!(without.len(), 2);
+        assert_eq!(without.without_by("z"), without);
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn dedup_values_by() {
+        let mut map: IndexMap<i32, &str> = [
+            (1, "up"),
+            (2, "up"),
+            (3, "down"),
+            (4, "down"),
+            (5, "down"),
+            (6, "up"),
+            (7, "up"),
+        ]
+        .into_iter()
+        .collect();
+        map.remove(&3);
+
+        map.dedup_values_by(|a, b| a == b);
+        assert_eq!(keys(&map), vec![1, 4, 6]);
+        assert_eq!(map.get(&4), Some(&"down"));
+        assert_eq!(map.get(&2), None);
+        assert_eq!(map.get_index_of(&6), Some(2));
+    }
+
+    #[test]
+    fn dedup_values_by_previous_survivor() {
+        let mut map: IndexMap<i32, i32> = [10, 11, 12, 20, 21, 30]
+            .into_iter()
+            .zip(0..)
+            .map(|(v, k)| (k, v))
+            .collect();
+
+        map.dedup_values_by(|previous, current| current - previous < 2);
+        assert_eq!(
+            map.values().copied().collect::<Vec<_>>(),
+            vec![10, 12, 20, 30]
+        );
+    }
+
+    #[test]
+    fn into_sorted_vec() {
+        let mut map: IndexMap<u32, u32> = (0..100).map(|i| ((i * 37) % 100, i)).collect();
+        for key in [0, 13, 99, 50] {
+            map.remove(&key);
+        }
+        let expected: Vec<_> = (0..100)
+            .filter(|k| ![0, 13, 99, 50].contains(k))
+            .map(|k| (k, map[&k]))
+            .collect();
+
+        assert_eq!(map.clone().into_sorted_vec(), expected);
+        assert!(map.into_sorted_iter().eq(expected));
+    }
+
+    #[test]
+    fn get_equivalent_case_insensitive() {
+        struct CaseInsensitive<'a>(&'a str);
+
+        impl Hash for CaseInsensitive<'_> {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.to_ascii_lowercase().hash(state);
+            }
+        }
+
+        impl Equivalent<String> for CaseInsensitive<'_> {
+            fn equivalent(&self, key: &String) -> bool {
+                self.0.eq_ignore_ascii_case(key)
+            }
+        }
+
+        let map: IndexMap<String, i32> = [("abc".to_string(), 1), ("def".to_string(), 2)]
+            .into_iter()
+            .collect();
+
+        assert_eq!(map.get_equivalent(&CaseInsensitive("ABC")), Some(&1));
+        assert_eq!(map.get_equivalent(&CaseInsensitive("dEf")), Some(&2));
+        assert_eq!(map.get_equivalent(&CaseInsensitive("xyz")), None);
+        assert!(map.contains_equivalent(&CaseInsensitive("Abc")));
+        assert!(map.contains_equivalent("abc"));
+        assert!(!map.contains_equivalent("ABC"));
+    }
+
+    #[test]
+    fn insert_before_and_after() {
+        let mut map: IndexMap<char, i32> = "bcd".chars().zip(0..).collect();
+
+        assert!(map.insert_before(&'b', 'a', 10));
+        assert!(map.insert_after(&'d', 'e', 11));
+        assert!(map.insert_after(&'b', 'x', 12));
+        assert_eq!(keys(&map), vec!['a', 'b', 'x', 'c', 'd', 'e']);
+        assert_eq!(map.get(&'x'), Some(&12));
+        assert_eq!(map.get_index_of(&'e'), Some(5));
+    }
+
+    #[test]
+    fn insert_before_and_after_move_existing() {
+        let mut map: IndexMap<char, i32> = "abcde".chars().zip(0..).collect();
+
+        assert!(map.insert_before(&'d', 'a', 10));
+        assert_eq!(keys(&map), vec!['b', 'c', 'a', 'd', 'e']);
+        assert!(map.insert_after(&'b', 'e', 40));
+        assert_eq!(keys(&map), vec!['b', 'e', 'c', 'a', 'd']);
+        assert!(map.insert_after(&'c', 'c', 20));
+        assert_eq!(keys(&map), vec!['b', 'e', 'c', 'a', 'd']);
+        assert_eq!(map.get(&'c'), Some(&20));
+        assert_eq!(map.len(), 5);
+    }
+
+    #[test]
+    fn insert_before_colliding_key() {
+        use std::hash::BuildHasherDefault;
+
+        use crate::hasher::CollidingHasher;
+
+        type CollidingMap = super::IndexMap<u64, i32, BuildHasherDefault<CollidingHasher<4>>>;
+
+        let mut map: CollidingMap = (0..4).zip(0..).collect();
+
+        // 4 collides with 0, which makes way for it.
+        assert!(map.insert_before(&3, 4, 40));
+        assert!(map.keys().eq(&[1, 2, 4, 3]));
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.get(&0), None);
+        assert_eq!(map.get_index_of(&4), Some(2));
+    }
+
+    #[test]
+    fn insert_after_colliding_key() {
+        use std::hash::BuildHasherDefault;
+
+        use crate::hasher::CollidingHasher;
+
+        type CollidingMap = super::IndexMap<u64, i32, BuildHasherDefault<CollidingHasher<4>>>;
+
+        let mut map: CollidingMap = (0..4).zip(0..).collect();
+
+        // 5 collides with 1, which makes way for it.
+        assert!(map.insert_after(&2, 5, 50));
+        assert!(map.keys().eq(&[0, 2, 5, 3]));
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.get(&1), None);
+        assert_eq!(map.get_index_of(&5), Some(2));
+
+        // Inserting after the colliding key itself keeps its position.
+        assert!(map.insert_after(&3, 7, 70));
+        assert!(map.keys().eq(&[0, 2, 5, 7]));
+    }
+
+    #[test]
+    fn insert_before_missing_anchor() {
+        let mut map: IndexMap<char, i32> = "ab".chars().zip(0..).collect();
+
+        assert!(!map.insert_before(&'z', 'c', 2));
+        assert!(!map.insert_after(&'z', 'a', 9));
+        assert_eq!(keys(&map), vec!['a', 'b']);
+        assert_eq!(map.get(&'a'), Some(&0));
+    }
+
+    #[test]
+    fn rotate_left_and_right() {
+        let mut map: IndexMap<i32, i32> = (0..5).map(|i| (i, i * 10)).collect();
+
+        map.rotate_left(2);
+        assert_eq!(keys(&map), vec![2, 3, 4, 0, 1]);
+        map.rotate_right(3);
+        assert_eq!(keys(&map), vec![4, 0, 1, 2, 3]);
+        map.rotate_left(11);
+        assert_eq!(keys(&map), vec![0, 1, 2, 3, 4]);
+        map.rotate_right(5);
+        assert_eq!(keys(&map), vec![0, 1, 2, 3, 4]);
+
+        for key in 0..5 {
+            assert_eq!(map.get(&key), Some(&(key * 10)));
+        }
+    }
+
+    #[test]
+    fn rotate_with_removed_entries() {
+        let mut map: IndexMap<i32, i32> = (0..6).map(|i| (i, i)).collect();
+        map.remove(&0);
+        map.remove(&3);
+
+        map.rotate_right(1);
+        assert_eq!(keys(&map), vec![5, 1, 2, 4]);
+        assert_eq!(map.get_index_of(&2), Some(2));
+        assert_eq!(map.get(&3), None);
+
+        let mut empty: IndexMap<i32, i32> = IndexMap::new();
+        empty.rotate_left(3);
+        empty.rotate_right(3);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn eq_std_maps_ignores_order() {
+        let map: IndexMap<&str, i32> = [("b", 2), ("a", 1), ("c", 3)].into_iter().collect();
+        let hash_map: HashMap<&str, i32> = [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+        let btree_map: BTreeMap<&str, i32> = hash_map.clone().into_iter().collect();
+
+        assert_eq!(map, hash_map);
+        assert_eq!(hash_map, map);
+        assert_eq!(map, btree_map);
+        assert_eq!(btree_map, map);
+
+        let map = map.without(&"c");
+        assert_ne!(map, hash_map);
+        assert_ne!(btree_map, map);
+
+        let map = map.update("c", 30);
+        assert_ne!(map, hash_map);
+        assert_ne!(map, btree_map);
+        assert_ne!(hash_map, map);
+    }
+
+    #[test]
+    fn get_disjoint_indices_mut() {
+        let mut map: IndexMap<char, i32> = "abcde".chars().zip(0..).collect();
+        map.remove(&'b');
+
+        let [last, first] = map.get_disjoint_indices_mut([3, 0]).unwrap();
+        std::mem::swap(first, last);
+        assert_eq!(map.get(&'a'), Some(&4));
+        assert_eq!(map.get(&'e'), Some(&0));
+
+        let [c] = map.get_disjoint_indices_mut([1]).unwrap();
+        *c += 10;
+        assert_eq!(map.get(&'c'), Some(&12));
+    }
+
+    #[test]
+    fn get_disjoint_indices_mut_rejects_bad_indices() {
+        let mut map: IndexMap<char, i32> = "abc".chars().zip(0..).collect();
+        map.remove(&'c');
+
+        assert!(map.get_disjoint_indices_mut([0, 2]).is_none());
+        assert!(map.get_disjoint_indices_mut([5]).is_none());
+        assert!(map.get_disjoint_indices_mut([1, 0, 1]).is_none());
+        assert!(map.get_disjoint_indices_mut([0, 1]).is_some());
+    }
+
+    #[test]
+    fn tombstone_count_and_density() {
+        let mut map: IndexMap<i32, i32> = IndexMap::new();
+        assert_eq!(map.tombstone_count(), 0);
+        assert_eq!(map.density(), 1.0);
+
+        map.extend((0..8).map(|i| (i, i)));
+        map.remove(&1);
+        map.remove(&2);
+        let map = map.without(&5);
+        assert_eq!(map.tombstone_count(), 3);
+        assert_eq!(map.density(), 5.0 / 8.0);
+
+        let mut map = map.update(1, 1);
+        assert_eq!(map.tombstone_count(), 3);
+        assert_eq!(map.density(), 6.0 / 9.0);
+
+        map.compact();
+        assert_eq!(map.tombstone_count(), 0);
+        assert_eq!(map.density(), 1.0);
+    }
+
+    #[test]
+    fn replace_key() {
+        let mut map: IndexMap<&str, i32> = [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+
+        assert_eq!(map.replace_key(&"b", "x"), Ok(()));
+        assert_eq!(keys(&map), vec!["a", "x", "c"]);
+        assert_eq!(map.get(&"x"), Some(&2));
+        assert_eq!(map.get(&"b"), None);
+        assert_eq!(map.get_index_of(&"x"), Some(1));
+
+        assert_eq!(map.replace_key(&"x", "x"), Ok(()));
+        assert_eq!(keys(&map), vec!["a", "x", "c"]);
+
+        map.insert("b", 20);
+        assert_eq!(keys(&map), vec!["a", "x", "c", "b"]);
+    }
+
+    #[test]
+    fn replace_key_onto_colliding_key() {
+        use std::hash::BuildHasherDefault;
+
+        use crate::hasher::CollidingHasher;
+
+        // Odd keys all collide, as do even keys.
+        type ParityMap = super::IndexMap<u64, &'static str, BuildHasherDefault<CollidingHasher<2>>>;
+
+        let mut map = ParityMap::new();
+        map.insert(1, "a");
+        map.insert(2, "b");
+
+        map.replace_key(&2, 3).unwrap();
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(&1), None);
+        assert_eq!(map.get(&3), Some(&"b"));
+        assert!(map.iter().eq([(&3, &"b")]));
+    }
+
+    #[test]
+    fn replace_key_errors() {
+        let mut map: IndexMap<&str, i32> = [("a", 1), ("b", 2)].into_iter().collect();
+
+        assert_eq!(map.replace_key(&"z", "y"), Err(RenameError::NotFound));
+        assert_eq!(map.replace_key(&"a", "b"), Err(RenameError::AlreadyExists));
+        assert_eq!(keys(&map), vec!["a", "b"]);
+        assert_eq!(map.get(&"a"), Some(&1));
+        assert_eq!(map.get(&"b"), Some(&2));
+    }
+
+    #[test]
+    fn chunks() {
+        let map: IndexMap<i32, i32> = (0..8).map(|i| (i, i)).collect();
+        let map = map.without(&3);
+        let chunk_keys: Vec<Vec<i32>> = map
+            .chunks(3)
+            .map(|chunk| chunk.into_iter().map(|(k, _)| *k).collect())
+            .collect();
+
+        assert_eq!(chunk_keys, vec![vec![0, 1, 2], vec![4, 5, 6], vec![7]]);
+        assert_eq!(map.chunks(10).count(), 1);
+        assert_eq!(IndexMap::<i32, i32>::new().chunks(3).count(), 0);
+    }
+
+    #[test]
+    fn windows() {
+        let map: IndexMap<i32, i32> = (0..8).map(|i| (i, i)).collect();
+        let map = map.without(&3);
+        let window_keys: Vec<Vec<i32>> = map
+            .windows(3)
+            .map(|window| window.into_iter().map(|(k, _)| *k).collect())
+            .collect();
+
+        assert_eq!(
+            window_keys,
+            vec![
+                vec![0, 1, 2],
+                vec![1, 2, 4],
+                vec![2, 4, 5],
+                vec![4, 5, 6],
+                vec![5, 6, 7],
+            ]
+        );
+        assert_eq!(map.windows(7).count(), 1);
+        assert_eq!(map.windows(8).count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be non-zero")]
+    fn chunks_of_zero() {
+        let map: IndexMap<i32, i32> = (0..3).map(|i| (i, i)).collect();
+        let _ = map.chunks(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be non-zero")]
+    fn windows_of_zero() {
+        let map: IndexMap<i32, i32> = (0..3).map(|i| (i, i)).collect();
+        let _ = map.windows(0);
+    }
+
+    #[test]
+    fn from_sorted_unique_iter() {
+        let map: IndexMap<i32, char> = IndexMap::from_sorted_unique_iter((0..26).zip('a'..='z'));
+
+        assert_eq!(map.len(), 26);
+        assert_eq!(keys(&map), (0..26).collect::<Vec<_>>());
+        assert_eq!(map.get(&2), Some(&'c'));
+        assert_eq!(map.get_index_of(&25), Some(25));
+        assert_eq!(map, (0..26).zip('a'..='z').collect::<IndexMap<_, _>>());
+    }
+
+    #[test]
+    fn try_from_sorted_iter() {
+        let map: IndexMap<i32, i32> =
+            IndexMap::try_from_sorted_iter([(1, 10), (3, 30), (7, 70)]).unwrap();
+        assert_eq!(keys(&map), vec![1, 3, 7]);
+        assert_eq!(map.get(&3), Some(&30));
+
+        let duplicate = IndexMap::<i32, i32>::try_from_sorted_iter([(1, 10), (3, 30), (3, 31)]);
+        assert_eq!(
+            duplicate.unwrap_err(),
+            FromSortedError::Unsorted { index: 2 }
+        );
+
+        let unsorted = IndexMap::<i32, i32>::try_from_sorted_iter([(2, 0), (1, 0)]);
+        assert_eq!(
+            unsorted.unwrap_err(),
+            FromSortedError::Unsorted { index: 1 }
+        );
+    }
+
+    #[test]
+    fn from_sorted_iter_with_colliding_keys() {
+        use std::hash::BuildHasherDefault;
+
+        use crate::hasher::CollidingHasher;
+
+        type CollidingMap = super::IndexMap<u64, char, BuildHasherDefault<CollidingHasher<4>>>;
+
+        // 5 collides with 1: the keys are sorted and unique, but only one of
+        // the two can be kept.
+        let pairs = [(0, 'a'), (1, 'b'), (2, 'c'), (5, 'd')];
+
+        let map = CollidingMap::from_sorted_unique_iter(pairs);
+        assert_eq!(map.len(), 3);
+        assert!(map.keys().eq(&[0, 5, 2]));
+        assert_eq!(map.get(&1), None);
+        assert_eq!(map.get(&5), Some(&'d'));
+
+        let err = CollidingMap::try_from_sorted_iter(pairs).unwrap_err();
+        assert_eq!(err, FromSortedError::HashCollision { index: 3 });
+        assert_eq!(
+            err.to_string(),
+            "key at position 3 collides with an earlier key"
+        );
+    }
+
+    #[test]
+    fn for_each_and_fold() {
+        let map: IndexMap<i32, i32> = (1..=5).map(|i| (i, i * 10)).collect();
+        let map = map.without(&3);
+
+        let mut seen = Vec::new();
+        map.for_each(|k, _| seen.push(*k));
+        assert_eq!(seen, vec![1, 2, 4, 5]);
+
+        assert_eq!(map.fold(0, |acc, _, v| acc + v), 120);
+        assert_eq!(
+            map.try_fold(0, |acc, k, v| Ok::<_, ()>(acc + k * v)),
+            Ok(460)
+        );
+    }
+
+    #[test]
+    fn try_fold_stops_at_error() {
+        let map: IndexMap<i32, i32> = (1..=5).map(|i| (i, i)).collect();
+
+        let mut visited = 0;
+        let result = map.try_fold(0, |acc, k, v| {
+            visited += 1;
+            if *k == 3 {
+                Err(format!("bad key {k}"))
+            } else {
+                Ok(acc + v)
+            }
+        });
+
+        assert_eq!(result, Err("bad key 3".to_string()));
+        assert_eq!(visited, 3);
+    }
+
+    #[test]
+    fn new_from_with_capacity() {
+        let mut map: IndexMap<i32, i32> = (0..50).map(|i| (i, i)).collect();
+        map.reserve(100);
+
+        let sibling = map.new_from_with_capacity();
+        assert!(sibling.is_empty());
+        assert!(sibling.capacity() >= map.capacity());
+        assert_eq!(map.new_from().capacity(), 0);
+    }
+
+    #[test]
+    fn reverse() {
+        let mut map: IndexMap<char, i32> = "abcde".chars().zip(0..).collect();
+        map.remove(&'c');
+
+        map.reverse();
+        assert_eq!(keys(&map), vec!['e', 'd', 'b', 'a']);
+        assert_eq!(map.tombstone_count(), 0);
+        for (key, value) in "abde".chars().zip([0, 1, 3, 4]) {
+            assert_eq!(map.get(&key), Some(&value));
+        }
+        assert_eq!(map.get(&'c'), None);
+        assert_eq!(map.get_index_of(&'a'), Some(3));
+
+        map.reverse();
+        assert_eq!(keys(&map), vec!['a', 'b', 'd', 'e']);
+    }
+
+    #[test]
+    fn from_iter_with_counts_words() {
+        let text = "the cat and the dog and the bird";
+        let counts: IndexMap<&str, usize> = IndexMap::from_iter_with(
+            text.split(' ').map(|word| (word, 1)),
+            || 0,
+            |count, n| *count += n,
+        );
+
+        assert_eq!(keys(&counts), vec!["the", "cat", "and", "dog", "bird"]);
+        assert_eq!(counts.get(&"the"), Some(&3));
+        assert_eq!(counts.get(&"and"), Some(&2));
+        assert_eq!(counts.get(&"bird"), Some(&1));
+        assert_eq!(counts.values().sum::<usize>(), 8);
+    }
 
-            Self {
-                indices,
-                entries,
-                hash_builder: self.hash_builder.clone(),
+    #[test]
+    fn from_iter_with_collects_groups() {
+        let groups: IndexMap<bool, Vec<i32>> =
+            IndexMap::from_iter_with((1..=6).map(|i| (i % 2 == 0, i)), Vec::new, Vec::push);
+
+        assert_eq!(keys(&groups), vec![false, true]);
+        assert_eq!(groups.get(&false), Some(&vec![1, 3, 5]));
+        assert_eq!(groups.get(&true), Some(&vec![2, 4, 6]));
+    }
+
+    #[test]
+    fn hashes_differing_in_upper_bits_stay_distinct() {
+        use std::hash::BuildHasherDefault;
+
+        use crate::hasher::IdentityHasher;
+
+        type IdentityMap = super::IndexMap<u64, &'static str, BuildHasherDefault<IdentityHasher>>;
+
+        let low = 7;
+        let high = 7 | (1 << 32);
+        let mut map = IdentityMap::new();
+        map.insert(low, "low");
+        map.insert(high, "high");
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&low), Some(&"low"));
+        assert_eq!(map.get(&high), Some(&"high"));
+
+        map.remove(&low);
+        assert_eq!(map.get(&low), None);
+        assert_eq!(map.get(&high), Some(&"high"));
+    }
+
+    #[test]
+    fn iterators_stay_exhausted() {
+        fn assert_fused<I: FusedIterator>(mut iter: I) {
+            for _ in iter.by_ref() {}
+            for _ in 0..3 {
+                assert!(iter.next().is_none());
             }
         }
+
+        let mut map: IndexMap<i32, i32> = (0..6).map(|i| (i, i)).collect();
+        map.remove(&0);
+        map.remove(&3);
+        map.remove(&5);
+
+        assert_fused(map.iter());
+        assert_fused(map.keys());
+        assert_fused(map.values());
+        assert_fused(map.clone().into_iter());
+
+        let mut iter = map.iter();
+        assert_eq!(iter.next_back(), Some((&4, &4)));
+        assert_eq!(iter.next(), Some((&1, &1)));
+        assert_eq!(iter.next(), Some((&2, &2)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+        assert_eq!(iter.next(), None);
     }
 
-    pub fn without(&self, key: &K) -> Self {
-        let hash = self.hash(key);
+    #[test]
+    fn increment_returns_running_totals() {
+        let mut counts: IndexMap<&str, u32> = IndexMap::new();
+        for (word, expected) in [("a", 1), ("b", 1), ("a", 2), ("a", 3), ("b", 2)] {
+            assert_eq!(*counts.increment(word, 1), expected);
+        }
+        assert_eq!(*counts.increment("c", 5), 5);
+        assert_eq!(*counts.increment("b", 10), 12);
 
-        if let Some(idx) = self.indices.get(&hash).copied() {
-            let indices = self.indices.without(&hash);
-            let entries = self.entries.update(idx, None);
+        let pairs: Vec<_> = counts.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(pairs, vec![("a", 3), ("b", 12), ("c", 5)]);
+    }
 
-            Self {
-                indices,
-                entries,
-                hash_builder: self.hash_builder.clone(),
-            }
-        } else {
-            self.clone()
+    #[test]
+    fn split_first_and_last() {
+        let map: IndexMap<i32, char> = (0..6).zip('a'..).collect();
+        let map = map.without(&0).without(&5);
+
+        let (head, rest) = map.split_first().unwrap();
+        assert_eq!(head, (&1, &'b'));
+        assert_eq!(keys(&rest), vec![2, 3, 4]);
+        assert_eq!(rest.get(&1), None);
+
+        let (tail, rest) = map.split_last().unwrap();
+        assert_eq!(tail, (&4, &'e'));
+        assert_eq!(keys(&rest), vec![1, 2, 3]);
+        assert_eq!(rest.get(&4), None);
+        assert_eq!(rest.entries.len(), 4);
+
+        assert_eq!(keys(&map), vec![1, 2, 3, 4]);
+        assert_eq!(map.get(&1), Some(&'b'));
+
+        let mut rest = map;
+        let mut seen = Vec::new();
+        while let Some(((k, _), next)) = rest.split_first() {
+            seen.push(*k);
+            rest = next;
         }
+        assert_eq!(seen, vec![1, 2, 3, 4]);
+        assert!(rest.is_empty());
+
+        let empty: IndexMap<i32, char> = IndexMap::new();
+        assert!(empty.split_first().is_none());
+        assert!(empty.split_last().is_none());
     }
 
-    pub fn remove(&mut self, key: &K) {
-        let hash = self.hash(key);
+    #[test]
+    fn same_seed_same_hashes() {
+        type SeededMap = super::IndexMap<&'static str, i32, DeterministicState>;
 
-        if let Some(idx) = self.indices.get(&hash).copied() {
-            self.indices.remove(&hash);
-            self.entries.remove(idx);
+        let mut a = SeededMap::with_seed(7);
+        let mut b = SeededMap::with_seed(7);
+        for key in ["x", "y", "z"] {
+            a.insert(key, 1);
+            b.insert(key, 2);
+            assert_eq!(a.hash(&key), b.hash(&key));
         }
+        assert!(a.keys().eq(&["x", "y", "z"]));
+        assert_eq!(a.hasher(), &DeterministicState::new(7));
+
+        let c = SeededMap::with_seed(8);
+        assert_ne!(a.hash(&"x"), c.hash(&"x"));
+        assert_eq!(a.hash(&42u64).get(), 8_718_785_269_080_554_627);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn physical_len_and_reserve_entries() {
+        let mut map: IndexMap<u32, u32> = (0..10).map(|i| (i, i)).collect();
+        assert_eq!(map.physical_len(), map.len());
 
-    type IndexMap<K, V> = super::IndexMap<K, V, RandomState>;
+        for key in [1, 4, 5] {
+            map.remove(&key);
+            assert!(map.physical_len() >= map.len());
+        }
+        let map2 = map.without(&9);
+        assert!(map2.physical_len() >= map2.len());
+        assert_eq!(map.len(), 7);
+        assert_eq!(map.physical_len(), 10);
+
+        map.reserve(5);
+        assert_eq!(map.capacity(), 12);
+        map.reserve_entries(5);
+        assert_eq!(map.capacity(), 15);
+
+        map.compact();
+        assert_eq!(map.physical_len(), map.len());
+        assert_eq!(map.len(), 7);
+    }
 
     #[test]
-    fn new_map_is_empty() {
-        let map: IndexMap<i32, String> = IndexMap::new();
-        assert!(map.iter().next().is_none());
+    fn drain_range_shifts_the_rest() {
+        let original: IndexMap<i32, i32> = (0..8).map(|i| (i, i * 10)).collect();
+        let original = original.without(&2).without(&7);
+        assert_eq!(keys(&original), vec![0, 1, 3, 4, 5, 6]);
+
+        let mut map = original.clone();
+        let drained: Vec<_> = map.drain_range(1..4).collect();
+        assert_eq!(drained, vec![(1, 10), (3, 30), (4, 40)]);
+        assert_eq!(keys(&map), vec![0, 5, 6]);
+        assert_eq!(map.get_index_of(&6), Some(2));
+        assert_eq!(map.get(&3), None);
+        assert_eq!(map.len(), 3);
+
+        let mut map = original.clone();
+        assert_eq!(
+            map.drain_range(..2).collect::<Vec<_>>(),
+            vec![(0, 0), (1, 10)]
+        );
+        assert_eq!(keys(&map), vec![3, 4, 5, 6]);
+        assert_eq!(map.get_index_of(&3), Some(0));
+
+        let mut map = original.clone();
+        assert_eq!(
+            map.drain_range(4..).collect::<Vec<_>>(),
+            vec![(5, 50), (6, 60)]
+        );
+        assert_eq!(keys(&map), vec![0, 1, 3, 4]);
+        map.insert(9, 90);
+        assert_eq!(map.get_index_of(&9), Some(4));
+
+        let mut map = original.clone();
+        assert_eq!(map.drain_range(..).len(), 6);
+        assert!(map.is_empty());
+        assert_eq!(map.get(&0), None);
+
+        let mut map = original.clone();
+        assert_eq!(map.drain_range(2..=2).next(), Some((3, 30)));
+        assert_eq!(map.drain_range(3..3).next(), None);
+        assert_eq!(keys(&map), vec![0, 1, 4, 5, 6]);
+        assert_eq!(keys(&original), vec![0, 1, 3, 4, 5, 6]);
     }
 
     #[test]
-    fn get_nonexistent_key() {
-        let map: IndexMap<i32, String> = IndexMap::new();
-        assert_eq!(map.get(&1), None);
+    #[should_panic(expected = "out of bounds")]
+    fn drain_range_out_of_bounds() {
+        let mut map: IndexMap<i32, i32> = (0..3).map(|i| (i, i)).collect();
+        map.drain_range(1..5);
     }
 
     #[test]
-    fn update_empty_map() {
-        let map: IndexMap<i32, String> = IndexMap::new();
-        let updated = map.update(1, "one".to_string());
+    fn min_and_max_by_key_prefer_first() {
+        let scores: IndexMap<&str, (u32, u32)> = [
+            ("ann", (3, 20)),
+            ("bob", (7, 10)),
+            ("cat", (1, 30)),
+            ("dan", (7, 5)),
+            ("eve", (1, 40)),
+        ]
+        .into_iter()
+        .collect();
 
-        assert_eq!(updated.get(&1), Some(&"one".to_string()));
-        assert!(map.get(&1).is_none()); // Original map remains unchanged
+        assert_eq!(
+            scores.max_by_key(|_, (score, _)| *score),
+            Some((&"bob", &(7, 10)))
+        );
+        assert_eq!(
+            scores.min_by_key(|_, (score, _)| *score),
+            Some((&"cat", &(1, 30)))
+        );
+        assert_eq!(
+            scores.min_by_key(|_, (_, time)| *time),
+            Some((&"dan", &(7, 5)))
+        );
+        assert_eq!(
+            scores.max_by_key(|name, _| name.len()),
+            Some((&"ann", &(3, 20)))
+        );
+
+        let scores = scores.without(&"bob");
+        assert_eq!(
+            scores.max_by_key(|_, (score, _)| *score),
+            Some((&"dan", &(7, 5)))
+        );
+
+        let empty: IndexMap<&str, u32> = IndexMap::new();
+        assert_eq!(empty.max_by_key(|_, v| *v), None);
+        assert_eq!(empty.min_by_key(|_, v| *v), None);
     }
 
     #[test]
-    fn update_existing_key() {
-        let map: IndexMap<i32, String> = IndexMap::new();
-        let map = map.update(1, "one".to_string());
-        let updated = map.update(1, "new one".to_string());
+    fn clones_share_structure() {
+        use std::rc::Rc;
 
-        assert_eq!(updated.get(&1), Some(&"new one".to_string()));
-        assert_eq!(map.get(&1), Some(&"one".to_string())); // Original map remains unchanged
+        let mut map: IndexMap<u32, Rc<u32>> = (0..200).map(|i| (i, Rc::new(i))).collect();
+        map.remove(&50);
+
+        let copy = map.clone();
+        let mut target: IndexMap<u32, Rc<u32>> = (0..10).map(|i| (i, Rc::new(i))).collect();
+        target.clone_from(&map);
+
+        assert!(map.values().all(|v| Rc::strong_count(v) == 1));
+        assert_eq!(copy, map);
+        assert_eq!(target, map);
+        assert!(std::ptr::eq(copy.get(&7).unwrap(), map.get(&7).unwrap()));
+        assert!(std::ptr::eq(
+            target.get(&199).unwrap(),
+            map.get(&199).unwrap()
+        ));
     }
 
     #[test]
-    fn multiple_updates() {
-        let map: IndexMap<i32, String> = IndexMap::new();
-        let map = map.update(1, "one".to_string());
-        let map = map.update(2, "two".to_string());
-        let map = map.update(3, "three".to_string());
+    fn checked_lookups() {
+        let map: IndexMap<char, i32> = "abc".chars().zip(0..).collect();
+        let map = map.without(&'a');
 
-        assert_eq!(map.get(&1), Some(&"one".to_string()));
-        assert_eq!(map.get(&2), Some(&"two".to_string()));
-        assert_eq!(map.get(&3), Some(&"three".to_string()));
+        assert_eq!(map.get_index_checked(1), Ok((&'c', &2)));
+        let err = map.get_index_checked(2).unwrap_err();
+        assert_eq!(err, IndexError::OutOfRange { index: 2, len: 2 });
+        assert_eq!(err.to_string(), "index 2 out of range for length 2");
+
+        assert_eq!(map.get_checked(&'b'), Ok(&1));
+        assert_eq!(map.get_checked(&'a'), Err(KeyError));
+        assert_eq!(KeyError.to_string(), "key not found");
     }
 
     #[test]
-    fn insert_empty_map() {
-        let mut map: IndexMap<i32, String> = IndexMap::new();
-        map.insert(1, "one".to_string());
+    fn remove_by_index() {
+        let original: IndexMap<char, i32> = "abcdef".chars().zip(0..).collect();
+        let original = original.without(&'b');
+        assert_eq!(keys(&original), vec!['a', 'c', 'd', 'e', 'f']);
 
-        assert_eq!(map.get(&1), Some(&"one".to_string()));
+        let mut map = original.clone();
+        assert_eq!(map.shift_remove_index(0), Some(('a', 0)));
+        assert_eq!(map.shift_remove_index(1), Some(('d', 3)));
+        assert_eq!(map.shift_remove_index(2), Some(('f', 5)));
+        assert_eq!(map.shift_remove_index(2), None);
+        assert_eq!(keys(&map), vec!['c', 'e']);
+        assert_eq!(map.get_index_of(&'e'), Some(1));
+
+        let mut map = original.clone();
+        assert_eq!(map.swap_remove_index(0), Some(('a', 0)));
+        assert_eq!(keys(&map), vec!['f', 'c', 'd', 'e']);
+        assert_eq!(map.swap_remove_index(1), Some(('c', 2)));
+        assert_eq!(keys(&map), vec!['f', 'e', 'd']);
+        assert_eq!(map.swap_remove_index(2), Some(('d', 3)));
+        assert_eq!(map.swap_remove_index(2), None);
+        assert_eq!(keys(&map), vec!['f', 'e']);
+        assert_eq!(map.get_index_of(&'e'), Some(1));
+        assert_eq!(map.get(&'f'), Some(&5));
+        assert_eq!(map.len(), 2);
     }
 
     #[test]
-    fn insert_existing_key() {
-        let mut map: IndexMap<i32, String> = IndexMap::new();
-        map.insert(1, "one".to_string());
-        map.insert(1, "new one".to_string());
+    fn is_sorted_checks_live_entries() {
+        let mut map: IndexMap<i32, char> = [(4, 'd'), (1, 'a'), (3, 'c'), (2, 'b')]
+            .into_iter()
+            .collect();
+        assert!(!map.is_sorted_by_keys());
+        assert!(map
+            .without(&4)
+            .without(&1)
+            .is_sorted_by(|_, v1, _, v2| v1 >= v2));
 
-        assert_eq!(map.get(&1), Some(&"new one".to_string()));
+        map.sort_keys();
+        assert!(map.is_sorted_by_keys());
+        assert!(map.is_sorted_by(|_, v1, _, v2| v1 < v2));
+        assert!(!map.is_sorted_by(|k1, _, k2, _| k1 > k2));
+
+        map.remove(&2);
+        map.insert(0, 'z');
+        assert!(!map.is_sorted_by_keys());
+        assert!(IndexMap::<i32, char>::new().is_sorted_by_keys());
     }
 
     #[test]
-    fn multiple_inserts() {
-        let mut map: IndexMap<i32, String> = IndexMap::new();
-        map.insert(1, "one".to_string());
-        map.insert(2, "two".to_string());
-        map.insert(3, "three".to_string());
+    fn retain_keys_and_values() {
+        let mut map: IndexMap<u32, String> =
+            (0..8).map(|i| (i, "x".repeat(i as usize % 3))).collect();
+        map.remove(&6);
 
-        assert_eq!(map.get(&1), Some(&"one".to_string()));
-        assert_eq!(map.get(&2), Some(&"two".to_string()));
-        assert_eq!(map.get(&3), Some(&"three".to_string()));
+        map.retain_keys(|k| *k > 2);
+        assert_eq!(keys(&map), vec![3, 4, 5, 7]);
+        assert_eq!(map.get_index_of(&4), Some(1));
+
+        map.retain_values(|v| !v.is_empty());
+        assert_eq!(keys(&map), vec![4, 5, 7]);
+        assert_eq!(map.get(&5), Some(&"xx".to_string()));
+        assert_eq!(map.get(&3), None);
+        assert_eq!(map.get_index_of(&7), Some(2));
+        assert_eq!(map.len(), 3);
     }
 
     #[test]
-    fn iter_order() {
-        let map: IndexMap<i32, String> = IndexMap::new();
-        let map = map
-            .update(1, "one".to_string())
-            .update(2, "two".to_string())
-            .update(3, "three".to_string());
+    fn intersection_with_and_difference() {
+        let a: IndexMap<char, i32> = [('c', 3), ('a', 1), ('d', 4), ('b', 2)]
+            .into_iter()
+            .collect();
+        let b: IndexMap<char, i32> = [('b', 20), ('x', 0), ('c', 30)].into_iter().collect();
 
-        let items: Vec<_> = map.iter().collect();
-        assert_eq!(items.len(), 3);
+        let both = a.intersection_with(&b, |_, mine, theirs| mine + theirs);
+        let pairs: Vec<_> = both.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(pairs, vec![('c', 33), ('b', 22)]);
+        assert_eq!(keys(&a.difference(&b)), vec!['a', 'd']);
 
-        // Assuming insertion order is maintained
-        assert_eq!(items[0], (&1, &"one".to_string()));
-        assert_eq!(items[1], (&2, &"two".to_string()));
-        assert_eq!(items[2], (&3, &"three".to_string()));
+        let disjoint: IndexMap<char, i32> = [('y', 0), ('z', 0)].into_iter().collect();
+        assert!(a.intersection_with(&disjoint, |_, v, _| *v).is_empty());
+        assert_eq!(a.difference(&disjoint), a);
+
+        let doubled = a.intersection_with(&a, |_, v, w| v + w);
+        assert_eq!(keys(&doubled), vec!['c', 'a', 'd', 'b']);
+        assert_eq!(doubled.get(&'d'), Some(&8));
+        assert!(a.difference(&a).is_empty());
     }
 
     #[test]
-    fn with_string_keys() {
-        let map: IndexMap<String, i32> = IndexMap::new();
-        let map = map
-            .update("one".to_string(), 1)
-            .update("two".to_string(), 2);
+    fn size_hints_track_remaining_entries() {
+        let mut map: IndexMap<u32, u32> = (0..6).map(|i| (i, i)).collect();
+        map.remove(&0);
+        map.remove(&3);
 
-        assert_eq!(map.get(&"one".to_string()), Some(&1));
-        assert_eq!(map.get(&"two".to_string()), Some(&2));
+        let mut iter = map.iter();
+        assert_eq!(iter.size_hint(), (4, Some(4)));
+        iter.next();
+        assert_eq!(iter.size_hint(), (3, Some(3)));
+        iter.next_back();
+        assert_eq!(iter.len(), 2);
+        iter.by_ref().for_each(drop);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+
+        let mut keys = map.keys();
+        keys.next();
+        assert_eq!(keys.size_hint(), (3, Some(3)));
+        assert_eq!(map.values().size_hint(), (4, Some(4)));
+        assert_eq!(map.get_range(1..3).size_hint(), (2, Some(2)));
+        assert_eq!(map.chunks(3).size_hint(), (2, Some(2)));
+        assert_eq!(map.windows(3).size_hint(), (2, Some(2)));
+
+        let mut entries = map.entries();
+        assert_eq!(entries.len(), 4);
+        entries.next().unwrap().remove();
+        assert_eq!(entries.len(), 3);
+        drop(entries);
+
+        let mut owned = map.clone().into_iter();
+        assert_eq!(owned.size_hint(), (3, Some(3)));
+        owned.next();
+        assert_eq!(owned.size_hint(), (2, Some(2)));
     }
 
     #[test]
-    fn update_preserves_other_values() {
-        let map: IndexMap<i32, String> = IndexMap::new();
-        let map = map
-            .update(1, "one".to_string())
-            .update(2, "two".to_string());
-        let updated = map.update(1, "new one".to_string());
+    fn keys_as_set_keeps_order() {
+        let map: IndexMap<char, i32> = "dbca".chars().zip(0..).collect();
+        let map = map.without(&'b');
 
-        assert_eq!(updated.get(&1), Some(&"new one".to_string()));
-        assert_eq!(updated.get(&2), Some(&"two".to_string())); // Other values preserved
+        let set = map.keys_as_set();
+        assert!(set.iter().eq(map.keys()));
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&'a'));
+        assert!(!set.contains(&'b'));
+        assert_eq!(set.get_index_of(&'a'), Some(2));
     }
 
     #[test]
-    fn update_complex_type() {
-        #[derive(Clone, Hash, Eq, PartialEq)]
-        struct ComplexKey {
-            id: i32,
-            name: String,
-        }
+    fn shrink_to_keeps_a_floor() {
+        let mut map: IndexMap<u32, u32> = IndexMap::with_capacity(100);
+        map.extend((0..20).map(|i| (i, i)));
+        map.retain_keys(|k| k % 2 == 0);
+        map.remove(&4);
+        assert_eq!(map.capacity(), 100);
 
-        let map: IndexMap<ComplexKey, Vec<i32>> = IndexMap::new();
-        let key = ComplexKey {
-            id: 1,
-            name: "test".to_string(),
-        };
-        let map = map.update(key.clone(), vec![1, 2, 3]);
+        map.shrink_to(30);
+        assert_eq!(map.capacity(), 30);
+        assert_eq!(map.tombstone_count(), 0);
+        assert!(map.capacity() >= map.len());
 
-        assert_eq!(map.get(&key), Some(&vec![1, 2, 3]));
+        map.shrink_to(2);
+        assert_eq!(map.capacity(), map.len());
+        map.shrink_to(50);
+        assert_eq!(map.capacity(), map.len());
+
+        assert_eq!(keys(&map), vec![0, 2, 6, 8, 10, 12, 14, 16, 18]);
+        assert_eq!(map.get_index_of(&6), Some(2));
     }
 
     #[test]
-    fn update_zero_sized_types() {
-        #[derive(Clone, Hash, Eq, PartialEq)]
-        struct Empty;
+    #[should_panic(expected = "IndexMap: length overflow")]
+    fn push_past_max_len_panics() {
+        let mut map: IndexMap<u32, u32> = IndexMap::new();
+        // Stand in for a map holding `usize::MAX` entries.
+        map.len = usize::MAX;
+        map.insert(0, 0);
+    }
 
-        let map: IndexMap<Empty, Empty> = IndexMap::new();
-        let map = map.update(Empty, Empty);
+    #[test]
+    #[should_panic(expected = "IndexMap: length overflow")]
+    fn update_past_max_len_panics() {
+        let mut map: IndexMap<u32, u32> = IndexMap::new();
+        map.len = usize::MAX;
+        let _ = map.update(0, 0);
+    }
 
-        assert!(map.get(&Empty).is_some());
+    #[test]
+    fn grow_len_at_the_boundary() {
+        assert_eq!(grow_len(usize::MAX - 1), usize::MAX);
+        assert!(std::panic::catch_unwind(|| grow_len(usize::MAX)).is_err());
     }
 
     #[test]
-    fn test_without_from_empty_map() {
-        let map: IndexMap<i32, String> = IndexMap::new();
-        let result = map.without(&1);
-        assert!(result.get(&1).is_none());
-        assert_eq!(result.iter().count(), 0);
+    #[should_panic(expected = "IndexMap::reserve: capacity overflow")]
+    fn reserve_overflow_panics() {
+        let mut map: IndexMap<u32, u32> = (0..3).map(|i| (i, i)).collect();
+        map.reserve(usize::MAX - 3);
+        assert_eq!(map.capacity(), usize::MAX);
+        map.reserve(usize::MAX - 2);
     }
 
     #[test]
-    fn test_without_existing_key() {
-        let map = IndexMap::new()
-            .update(1, "one".to_string())
-            .update(2, "two".to_string());
+    #[should_panic(expected = "IndexMap::reserve_entries: capacity overflow")]
+    fn reserve_entries_overflow_panics() {
+        let mut map: IndexMap<u32, u32> = (0..3).map(|i| (i, i)).collect();
+        map.remove(&1);
+        map.reserve_entries(usize::MAX - 3);
+        map.reserve_entries(usize::MAX - 2);
+    }
 
-        let result = map.without(&1);
+    #[test]
+    fn range_bounds_saturate() {
+        let max = usize::MAX;
+        assert_eq!(range_bounds(&(..), max), (0, max));
+        assert_eq!(range_bounds(&(..=max), 3), (0, max));
+        assert_eq!(range_bounds(&(max - 1..=max), max), (max - 1, max));
+        assert_eq!(
+            range_bounds(&(Bound::Excluded(max), Bound::Unbounded), max),
+            (max, max)
+        );
+        assert_eq!(
+            range_bounds(&(Bound::Excluded(max - 1), Bound::Excluded(max)), 0),
+            (max, max)
+        );
 
-        assert!(result.get(&1).is_none());
-        assert_eq!(result.get(&2), Some(&"two".to_string()));
-        assert_eq!(result.iter().count(), 1);
+        let map: IndexMap<u32, u32> = (0..4).map(|i| (i, i)).collect();
+        assert_eq!(map.get_range(..=max).len(), 4);
+        assert_eq!(map.get_range(max..).len(), 0);
+        assert_eq!(
+            map.get_range((Bound::Excluded(max), Bound::Included(max)))
+                .len(),
+            0
+        );
+    }
 
-        // Original map should remain unchanged
-        assert_eq!(map.get(&1), Some(&"one".to_string()));
-        assert_eq!(map.get(&2), Some(&"two".to_string()));
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn drain_range_past_usize_max() {
+        let mut map: IndexMap<u32, u32> = (0..4).map(|i| (i, i)).collect();
+        map.drain_range((Bound::Excluded(usize::MAX), Bound::Unbounded));
     }
 
     #[test]
-    fn test_without_nonexistent_key() {
-        let map = IndexMap::new().update(1, "one".to_string());
+    fn get_or_insert_full_interns() {
+        let mut interner: IndexMap<&str, u32> = IndexMap::new();
+        let ids: Vec<_> = ["the", "cat", "the", "hat", "cat", "the"]
+            .into_iter()
+            .map(|word| {
+                let (id, count) = interner.get_or_insert_full(word, 0);
+                *count += 1;
+                id
+            })
+            .collect();
 
-        let result = map.without(&2);
+        assert_eq!(ids, vec![0, 1, 0, 2, 1, 0]);
+        assert_eq!(interner.get(&"the"), Some(&3));
+        assert_eq!(interner.get_index(2), Some((&"hat", &1)));
 
-        assert_eq!(result.get(&1), Some(&"one".to_string()));
-        assert_eq!(result.iter().count(), 1);
+        interner.remove(&"the");
+        assert_eq!(interner.get_or_insert_full("hat", 0).0, 1);
+        assert_eq!(interner.get_or_insert_full("mat", 0).0, 2);
     }
 
     #[test]
-    fn test_without_preserves_order() {
-        let map = IndexMap::new()
-            .update(1, "one".to_string())
-            .update(2, "two".to_string())
-            .update(3, "three".to_string())
-            .update(4, "four".to_string());
+    fn extend_with_maps() {
+        let mut map: IndexMap<char, i32> = [('a', 1), ('b', 2), ('c', 3)].into_iter().collect();
+        let first: IndexMap<char, i32> = [('d', 4), ('b', 20)].into_iter().collect();
+        let second: IndexMap<char, i32> = [('a', 10), ('e', 5), ('d', 40)].into_iter().collect();
+        let second = second.without(&'e');
 
-        let result = map.without(&2);
+        map.extend([first, second]);
 
-        let items: Vec<_> = result.iter().collect();
-        assert_eq!(items.len(), 3);
-        assert_eq!(items[0], (&1, &"one".to_string()));
-        assert_eq!(items[1], (&3, &"three".to_string()));
-        assert_eq!(items[2], (&4, &"four".to_string()));
+        let pairs: Vec<_> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(pairs, vec![('a', 10), ('b', 20), ('c', 3), ('d', 40)]);
+        assert_eq!(map.get_index_of(&'b'), Some(1));
+        assert_eq!(map.len(), 4);
     }
 
     #[test]
-    fn test_without_chaining() {
-        let map = IndexMap::new()
-            .update(1, "one".to_string())
-            .update(2, "two".to_string())
-            .update(3, "three".to_string());
+    fn zip_values_follows_self() {
+        let old: IndexMap<char, i32> = [('a', 1), ('b', 2), ('c', 3)].into_iter().collect();
+        let new: IndexMap<char, i32> = [('z', 0), ('c', 30), ('a', 1)].into_iter().collect();
 
-        let result = map.without(&1).without(&2);
+        let triples: Vec<_> = old.zip_values(&new).collect();
+        assert_eq!(
+            triples,
+            vec![
+                (&'a', &1, Some(&1)),
+                (&'b', &2, None),
+                (&'c', &3, Some(&30))
+            ]
+        );
 
-        assert!(result.get(&1).is_none());
-        assert!(result.get(&2).is_none());
-        assert_eq!(result.get(&3), Some(&"three".to_string()));
-        assert_eq!(result.iter().count(), 1);
+        let trimmed = old.without(&'a');
+        let triples: Vec<_> = new.zip_values(&trimmed).collect();
+        assert_eq!(
+            triples,
+            vec![(&'z', &0, None), (&'c', &30, Some(&3)), (&'a', &1, None)]
+        );
     }
 
     #[test]
-    fn test_without_all_elements() {
-        let map = IndexMap::new()
-            .update(1, "one".to_string())
-            .update(2, "two".to_string());
+    fn diff_lists_changes() {
+        let old: IndexMap<&str, i32> = [("a", 1), ("b", 2), ("c", 3), ("d", 4)]
+            .into_iter()
+            .collect();
+        let new: IndexMap<&str, i32> = [("e", 5), ("d", 4), ("c", 30), ("a", 1), ("f", 6)]
+            .into_iter()
+            .collect();
 
-        let result = map.without(&1).without(&2);
+        let changes: Vec<_> = old.diff(&new).collect();
+        assert_eq!(
+            changes,
+            vec![
+                Change::Removed {
+                    key: &"b",
+                    value: &2
+                },
+                Change::Updated {
+                    key: &"c",
+                    old: &3,
+                    new: &30
+                },
+                Change::Added {
+                    key: &"e",
+                    value: &5
+                },
+                Change::Added {
+                    key: &"f",
+                    value: &6
+                },
+            ]
+        );
 
-        assert_eq!(result.iter().count(), 0);
+        assert_eq!(old.diff(&old).count(), 0);
+        let empty = IndexMap::new();
+        assert_eq!(empty.diff(&old).count(), 4);
+        assert!(old
+            .diff(&empty)
+            .all(|change| matches!(change, Change::Removed { .. })));
     }
 
     #[test]
-    fn test_without_with_string_keys() {
-        let map = IndexMap::new()
-            .update("one".to_string(), 1)
-            .update("two".to_string(), 2);
+    fn logical_and_physical_positions() {
+        let map: IndexMap<u32, u32> = (0..10).map(|i| (i, i)).collect();
+        let map = map.without(&0).without(&4).without(&5).without(&9);
+        assert_eq!(keys(&map), vec![1, 2, 3, 6, 7, 8]);
 
-        let result = map.without(&"one".to_string());
+        let physical: Vec<_> = (0..map.len())
+            .map(|logical| map.logical_to_physical(logical).unwrap())
+            .collect();
+        assert_eq!(physical, vec![1, 2, 3, 6, 7, 8]);
+        for (logical, &slot) in physical.iter().enumerate() {
+            assert_eq!(map.physical_to_logical(slot), Some(logical));
+        }
 
-        assert!(result.get(&"one".to_string()).is_none());
-        assert_eq!(result.get(&"two".to_string()), Some(&2));
+        assert_eq!(map.logical_to_physical(6), None);
+        for empty in [0, 4, 5, 9, 10] {
+            assert_eq!(map.physical_to_logical(empty), None);
+        }
+
+        let mut compacted = map.clone();
+        compacted.compact();
+        assert_eq!(compacted.logical_to_physical(3), Some(3));
+        assert_eq!(compacted.physical_to_logical(5), Some(5));
     }
 
     #[test]
-    fn test_without_complex_key() {
-        #[derive(Clone, Hash, Eq, PartialEq, Debug)]
-        struct ComplexKey {
-            id: i32,
-            name: String,
-        }
+    fn without_all_matches_chained_without() {
+        let map: IndexMap<u32, u32> = (0..500).map(|i| (i * 7 % 500, i)).collect();
+        let doomed: Vec<u32> = (0..500).filter(|k| k % 5 < 3).collect();
+        assert_eq!(doomed.len(), 300);
 
-        let key1 = ComplexKey {
-            id: 1,
-            name: "first".to_string(),
-        };
-        let key2 = ComplexKey {
-            id: 2,
-            name: "second".to_string(),
-        };
+        let batched = map.without_all(&doomed);
+        let chained = doomed.iter().fold(map.clone(), |acc, key| acc.without(key));
 
-        let map = IndexMap::new()
-            .update(key1.clone(), "value1".to_string())
-            .update(key2.clone(), "value2".to_string());
+        assert_eq!(batched, chained);
+        assert!(batched.iter().eq(chained.iter()));
+        assert_eq!(batched.len(), 200);
+        assert_eq!(batched.tombstone_count(), 0);
+        assert_eq!(map.len(), 500);
+        assert_eq!(map.without_all([&1000]), map);
+    }
 
-        let result = map.without(&key1);
+    #[test]
+    fn count_if_any_all() {
+        let map: IndexMap<u32, &str> = [(1, "one"), (2, "two"), (3, "three"), (4, "four")]
+            .into_iter()
+            .collect();
+        let map = map.without(&2);
 
-        assert!(result.get(&key1).is_none());
-        assert_eq!(result.get(&key2), Some(&"value2".to_string()));
+        assert_eq!(map.count_if(|k, v| k % 2 == 1 || v.len() > 4), 2);
+        assert_eq!(map.count_if(|_, v| v.starts_with('t')), 1);
+
+        let mut seen = Vec::new();
+        assert!(map.any(|k, _| {
+            seen.push(*k);
+            *k == 3
+        }));
+        assert_eq!(seen, vec![1, 3]);
+
+        seen.clear();
+        assert!(!map.all(|k, _| {
+            seen.push(*k);
+            *k < 3
+        }));
+        assert_eq!(seen, vec![1, 3]);
+
+        assert!(map.all(|k, _| *k != 2));
+        assert!(!map.any(|_, v| v.is_empty()));
+        let empty: IndexMap<u32, &str> = IndexMap::new();
+        assert!(empty.all(|_, _| false));
+        assert!(!empty.any(|_, _| true));
+    }
+
+    #[test]
+    fn retain_after_without() {
+        let map: IndexMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+        let mut map = map.without(&0).without(&5);
+        map.retain(|_, v| *v > 2);
+
+        assert_eq!(map.len(), 6);
+        let keys: Vec<_> = map.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![3, 4, 6, 7, 8, 9]);
+
+        map.insert(10, 10);
+        assert_eq!(map.get(&10), Some(&10));
+        assert_eq!(map.get(&9), Some(&9));
     }
 }
@@ -0,0 +1,180 @@
+use std::hash::{BuildHasher, Hash};
+
+use imbl::shared_ptr::{RcK, SharedPointerKind};
+
+use super::{Entry, IndexMap};
+
+/// A mutable working copy of an [`IndexMap`], handed to the closure given to
+/// [`IndexMap::edit`].
+///
+/// Every change made through the editor applies to the same copy, so a batch
+/// of edits produces a single new map rather than one per step: a node shared
+/// with the original map is copied the first time it is changed, and changed
+/// in place afterwards.
+pub struct MapEditor<K, V, S, P: SharedPointerKind = RcK> {
+    map: IndexMap<K, V, S, P>,
+}
+
+impl<K, V, S, P: SharedPointerKind> MapEditor<K, V, S, P> {
+    pub(super) fn new(map: IndexMap<K, V, S, P>) -> Self {
+        Self { map }
+    }
+
+    pub(super) fn into_map(self) -> IndexMap<K, V, S, P> {
+        self.map
+    }
+
+    /// Returns the working copy as it stands, for the read-only parts of the
+    /// [`IndexMap`] API.
+    pub fn map(&self) -> &IndexMap<K, V, S, P> {
+        &self.map
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl<K, V, S, P: SharedPointerKind> MapEditor<K, V, S, P>
+where
+    K: Clone + Hash + Eq,
+    V: Clone,
+    S: Clone + BuildHasher,
+{
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let slot = self.map.find(key)?;
+        Some(&mut self.map.entries[slot].as_mut()?.value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// See [`IndexMap::insert`].
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map.insert(key, value)
+    }
+
+    /// See [`IndexMap::remove`].
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.map.remove(key)
+    }
+
+    /// See [`IndexMap::shift_remove`].
+    pub fn shift_remove(&mut self, key: &K) -> Option<V> {
+        self.map.shift_remove(key)
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S, P> {
+        self.map.entry(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::RandomState;
+
+    type IndexMap<K, V> = super::IndexMap<K, V, RandomState>;
+
+    #[test]
+    fn edit_matches_chained_updates() {
+        let original: IndexMap<&str, i32> = [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+
+        let chained = original
+            .update("d", 4)
+            .without(&"b")
+            .update("a", 10)
+            .update("e", 5)
+            .without(&"d");
+
+        let edited = original.edit(|editor| {
+            editor.insert("d", 4);
+            editor.remove(&"b");
+            *editor.get_mut(&"a").unwrap() = 10;
+            *editor.entry("e").or_default() += 5;
+            assert_eq!(editor.len(), 4);
+            editor.remove(&"d");
+            assert!(!editor.contains_key(&"d"));
+        });
+
+        assert_eq!(edited, chained);
+        assert!(edited.iter().eq(chained.iter()));
+        assert_eq!(edited.get_index_of(&"e"), Some(2));
+        assert_eq!(original.len(), 3);
+        assert_eq!(original.get(&"a"), Some(&1));
+    }
+
+    #[test]
+    fn edit_copies_shared_nodes_once() {
+        use std::cell::Cell;
+
+        thread_local! {
+            static CLONES: Cell<usize> = const { Cell::new(0) };
+        }
+
+        #[derive(Debug, PartialEq)]
+        struct Value(u32);
+
+        impl Clone for Value {
+            fn clone(&self) -> Self {
+                CLONES.with(|clones| clones.set(clones.get() + 1));
+                Value(self.0)
+            }
+        }
+
+        fn count_clones<T>(f: impl FnOnce() -> T) -> (T, usize) {
+            CLONES.with(|clones| clones.set(0));
+            let out = f();
+            (out, CLONES.with(Cell::get))
+        }
+
+        let original: IndexMap<u32, Value> = (0..32).map(|i| (i, Value(i))).collect();
+
+        let (chained, chained_clones) =
+            count_clones(|| (0..8).fold(original.clone(), |map, i| map.update(i, Value(i * 10))));
+        let (edited, edited_clones) = count_clones(|| {
+            original.edit(|editor| {
+                for i in 0..8 {
+                    editor.insert(i, Value(i * 10));
+                }
+            })
+        });
+
+        // Every `update` copies the nodes it touches out of the previous
+        // version, while the editor only copies them out of `original` the
+        // first time, and then changes its own copy in place.
+        assert_eq!(edited, chained);
+        assert!(
+            edited_clones * 4 <= chained_clones,
+            "edit cloned {edited_clones} values, chained updates {chained_clones}"
+        );
+        assert_eq!(original.get(&0), Some(&Value(0)));
+    }
+
+    #[test]
+    fn edit_with_shift_remove_and_reads() {
+        let original: IndexMap<u32, u32> = (0..5).map(|i| (i, i)).collect();
+
+        let edited = original.edit(|editor| {
+            editor.shift_remove(&1);
+            if let Some(value) = editor.get(&4).copied() {
+                editor.insert(5, value * 2);
+            }
+            assert_eq!(editor.map().get_index(0), Some((&0, &0)));
+            assert_eq!(editor.map().get_index(1), Some((&2, &2)));
+        });
+
+        let keys: Vec<_> = edited.keys().copied().collect();
+        assert_eq!(keys, vec![0, 2, 3, 4, 5]);
+        assert_eq!(edited.get(&5), Some(&8));
+        assert_eq!(original.len(), 5);
+    }
+}
@@ -0,0 +1,703 @@
+use std::borrow::Borrow;
+use std::hash::{BuildHasher, Hash};
+
+use imbl::shared_ptr::SharedPointerKind;
+
+use super::{Bucket, HashValue, IndexMap};
+
+/// A view into a single entry of an [`IndexMap`], which may be vacant or
+/// occupied. Returned by [`IndexMap::entry`].
+pub enum Entry<'a, K, V, S, P: SharedPointerKind> {
+    Occupied(OccupiedEntry<'a, K, V, S, P>),
+    Vacant(VacantEntry<'a, K, V, S, P>),
+}
+
+impl<'a, K, V, S, P: SharedPointerKind> Entry<'a, K, V, S, P> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+}
+
+impl<'a, K, V, S, P: SharedPointerKind> Entry<'a, K, V, S, P>
+where
+    K: Clone + Hash + Eq,
+    V: Clone,
+    S: Clone + BuildHasher,
+{
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+/// A handle to a missing key of an [`IndexMap`].
+pub struct VacantEntry<'a, K, V, S, P: SharedPointerKind> {
+    map: &'a mut IndexMap<K, V, S, P>,
+    hash: HashValue,
+    key: K,
+}
+
+impl<'a, K, V, S, P: SharedPointerKind> VacantEntry<'a, K, V, S, P> {
+    pub(super) fn new(map: &'a mut IndexMap<K, V, S, P>, hash: HashValue, key: K) -> Self {
+        Self { map, hash, key }
+    }
+
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Returns the logical position the entry would be inserted at.
+    pub fn index(&self) -> usize
+    where
+        K: Hash + Eq,
+        S: BuildHasher,
+    {
+        self.map.vacant_index(self.hash)
+    }
+}
+
+impl<'a, K, V, S, P: SharedPointerKind> VacantEntry<'a, K, V, S, P>
+where
+    K: Clone + Hash + Eq,
+    V: Clone,
+    S: Clone + BuildHasher,
+{
+    /// Inserts `value` at the end of the map, returning a mutable reference
+    /// to it.
+    ///
+    /// If a different key has the same hash, its entry is replaced in place
+    /// instead, as with [`IndexMap::insert`].
+    pub fn insert(self, value: V) -> &'a mut V {
+        let idx = self.map.put(Bucket {
+            hash: self.hash,
+            key: self.key,
+            value,
+        });
+        &mut self.map.entries[idx].as_mut().unwrap().value
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.insert(V::default())
+    }
+}
+
+/// A handle to a live entry of an [`IndexMap`].
+pub struct OccupiedEntry<'a, K, V, S, P: SharedPointerKind> {
+    map: &'a mut IndexMap<K, V, S, P>,
+    slot: usize,
+}
+
+impl<'a, K, V, S, P: SharedPointerKind> OccupiedEntry<'a, K, V, S, P> {
+    pub(super) fn new(map: &'a mut IndexMap<K, V, S, P>, slot: usize) -> Self {
+        Self { map, slot }
+    }
+
+    pub fn key(&self) -> &K {
+        &self.bucket().key
+    }
+
+    pub fn get(&self) -> &V {
+        &self.bucket().value
+    }
+
+    /// Returns the logical position of the entry.
+    pub fn index(&self) -> usize {
+        self.map.position(self.slot)
+    }
+
+    fn bucket(&self) -> &Bucket<K, V> {
+        self.map.entries[self.slot].as_ref().unwrap()
+    }
+}
+
+impl<'a, K, V, S, P: SharedPointerKind> OccupiedEntry<'a, K, V, S, P>
+where
+    K: Clone,
+    V: Clone,
+    S: Clone + BuildHasher,
+{
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.entries[self.slot].as_mut().unwrap().value
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.entries[self.slot].as_mut().unwrap().value
+    }
+
+    /// Replaces the value of the entry, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    /// Removes the entry, leaving its slot empty as [`IndexMap::remove`]
+    /// does, and returns its value.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    /// Like [`OccupiedEntry::remove`], but also returns the key.
+    pub fn remove_entry(self) -> (K, V) {
+        let bucket = self.map.entries.set(self.slot, None).unwrap();
+        self.map.indices.remove(&bucket.hash);
+        self.map.len -= 1;
+        (bucket.key, bucket.value)
+    }
+}
+
+/// A view into a single entry of an [`IndexMap`] looked up by a borrowed
+/// key, which may be vacant or occupied. Returned by [`IndexMap::entry_ref`].
+pub enum EntryByRef<'a, 'b, K, Q: ?Sized, V, S, P: SharedPointerKind> {
+    Occupied(OccupiedEntry<'a, K, V, S, P>),
+    Vacant(VacantEntryByRef<'a, 'b, K, Q, V, S, P>),
+}
+
+impl<'a, 'b, K, Q, V, S, P: SharedPointerKind> EntryByRef<'a, 'b, K, Q, V, S, P>
+where
+    K: Borrow<Q>,
+    Q: ?Sized,
+{
+    pub fn key(&self) -> &Q {
+        match self {
+            EntryByRef::Occupied(entry) => entry.key().borrow(),
+            EntryByRef::Vacant(entry) => entry.key(),
+        }
+    }
+}
+
+impl<'a, 'b, K, Q, V, S, P: SharedPointerKind> EntryByRef<'a, 'b, K, Q, V, S, P>
+where
+    K: Clone + Hash + Eq + for<'q> From<&'q Q>,
+    Q: ?Sized,
+    V: Clone,
+    S: Clone + BuildHasher,
+{
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            EntryByRef::Occupied(entry) => entry.into_mut(),
+            EntryByRef::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let EntryByRef::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+/// A handle to a missing key of an [`IndexMap`], holding the borrowed key
+/// until an owned one is needed for insertion.
+pub struct VacantEntryByRef<'a, 'b, K, Q: ?Sized, V, S, P: SharedPointerKind> {
+    map: &'a mut IndexMap<K, V, S, P>,
+    hash: HashValue,
+    key: &'b Q,
+}
+
+impl<'a, 'b, K, Q: ?Sized, V, S, P: SharedPointerKind> VacantEntryByRef<'a, 'b, K, Q, V, S, P> {
+    pub(super) fn new(map: &'a mut IndexMap<K, V, S, P>, hash: HashValue, key: &'b Q) -> Self {
+        Self { map, hash, key }
+    }
+
+    pub fn key(&self) -> &'b Q {
+        self.key
+    }
+
+    /// Returns the logical position the entry would be inserted at.
+    pub fn index(&self) -> usize
+    where
+        K: Hash + Eq,
+        S: BuildHasher,
+    {
+        self.map.vacant_index(self.hash)
+    }
+}
+
+impl<'a, 'b, K, Q, V, S, P: SharedPointerKind> VacantEntryByRef<'a, 'b, K, Q, V, S, P>
+where
+    K: Clone + Hash + Eq + for<'q> From<&'q Q>,
+    Q: ?Sized,
+    V: Clone,
+    S: Clone + BuildHasher,
+{
+    /// Converts the borrowed key into an owned one and inserts it with
+    /// `value` at the end of the map, returning a mutable reference to the
+    /// value.
+    pub fn insert(self, value: V) -> &'a mut V {
+        VacantEntry::new(self.map, self.hash, K::from(self.key)).insert(value)
+    }
+}
+
+/// A cursor over handles to the live entries of an [`IndexMap`], in order.
+/// Returned by [`IndexMap::entries`].
+///
+/// Each handle can mutate or remove its entry while the iteration goes on.
+/// Removing an entry only empties its slot, so the slots still to be visited
+/// are unaffected; the empty slots are compacted once, when the cursor is
+/// dropped.
+///
+/// Since the handles borrow the cursor, each one has to be dropped before
+/// the next is taken, so this is not an [`Iterator`]; loop over it with
+/// `while let Some(entry) = entries.next()` instead.
+pub struct EntriesMut<'a, K, V, S, P: SharedPointerKind>
+where
+    K: Clone + Hash + Eq,
+    V: Clone,
+    S: Clone + BuildHasher,
+{
+    map: &'a mut IndexMap<K, V, S, P>,
+    next: usize,
+    remaining: usize,
+    removed: bool,
+}
+
+impl<'a, K, V, S, P: SharedPointerKind> EntriesMut<'a, K, V, S, P>
+where
+    K: Clone + Hash + Eq,
+    V: Clone,
+    S: Clone + BuildHasher,
+{
+    pub(super) fn new(map: &'a mut IndexMap<K, V, S, P>) -> Self {
+        Self {
+            remaining: map.len,
+            map,
+            next: 0,
+            removed: false,
+        }
+    }
+
+    /// Returns a handle to the next live entry, or `None` once every entry
+    /// has been visited.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<EntryRef<'_, K, V, S, P>> {
+        while self.next < self.map.entries.len() {
+            let slot = self.next;
+            self.next += 1;
+            if self.map.entries[slot].is_some() {
+                self.remaining -= 1;
+                return Some(EntryRef {
+                    map: &mut *self.map,
+                    slot,
+                    removed: &mut self.removed,
+                });
+            }
+        }
+        None
+    }
+
+    /// Returns the number of live entries still to be visited.
+    pub fn len(&self) -> usize {
+        self.remaining
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+impl<K, V, S, P: SharedPointerKind> Drop for EntriesMut<'_, K, V, S, P>
+where
+    K: Clone + Hash + Eq,
+    V: Clone,
+    S: Clone + BuildHasher,
+{
+    fn drop(&mut self) {
+        if self.removed {
+            self.map.compact();
+        }
+    }
+}
+
+/// A handle to a live entry of an [`IndexMap`], yielded by
+/// [`EntriesMut::next`].
+pub struct EntryRef<'a, K, V, S, P: SharedPointerKind> {
+    map: &'a mut IndexMap<K, V, S, P>,
+    slot: usize,
+    removed: &'a mut bool,
+}
+
+impl<'a, K, V, S, P: SharedPointerKind> EntryRef<'a, K, V, S, P> {
+    pub fn key(&self) -> &K {
+        &self.bucket().key
+    }
+
+    pub fn get(&self) -> &V {
+        &self.bucket().value
+    }
+
+    fn bucket(&self) -> &Bucket<K, V> {
+        self.map.entries[self.slot].as_ref().unwrap()
+    }
+}
+
+impl<'a, K, V, S, P: SharedPointerKind> EntryRef<'a, K, V, S, P>
+where
+    K: Clone,
+    V: Clone,
+    S: Clone + BuildHasher,
+{
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.entries[self.slot].as_mut().unwrap().value
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.entries[self.slot].as_mut().unwrap().value
+    }
+
+    /// Removes the entry, leaving its slot empty until the [`EntriesMut`] it
+    /// came from is dropped, and returns its value.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    /// Like [`EntryRef::remove`], but also returns the key.
+    pub fn remove_entry(self) -> (K, V) {
+        let bucket = self.map.entries.set(self.slot, None).unwrap();
+        self.map.indices.remove(&bucket.hash);
+        self.map.len -= 1;
+        *self.removed = true;
+        (bucket.key, bucket.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::RandomState;
+
+    use super::{Entry, EntryByRef};
+
+    type IndexMap<K, V> = super::IndexMap<K, V, RandomState>;
+
+    #[test]
+    fn first_entry_mutate_and_remove() {
+        let mut map: IndexMap<char, i32> = "abcd".chars().zip(0..).collect();
+        map.remove(&'a');
+
+        let mut entry = map.first_entry().unwrap();
+        assert_eq!(*entry.key(), 'b');
+        assert_eq!(entry.index(), 0);
+        *entry.get_mut() += 10;
+        assert_eq!(entry.insert(20), 11);
+        assert_eq!(*entry.get(), 20);
+
+        assert_eq!(map.get(&'b'), Some(&20));
+
+        assert_eq!(map.first_entry().unwrap().remove(), 20);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&'b'), None);
+        assert_eq!(map.first(), Some((&'c', &2)));
+        assert_eq!(map.get_index_of(&'c'), Some(0));
+    }
+
+    #[test]
+    fn last_entry_mutate_and_remove() {
+        let mut map: IndexMap<char, i32> = "abcd".chars().zip(0..).collect();
+        map.remove(&'d');
+
+        let entry = map.last_entry().unwrap();
+        assert_eq!(*entry.key(), 'c');
+        assert_eq!(entry.index(), 2);
+        *entry.into_mut() = 30;
+        assert_eq!(map.get(&'c'), Some(&30));
+
+        assert_eq!(map.last_entry().unwrap().remove_entry(), ('c', 30));
+        assert_eq!(map.last(), Some((&'b', &1)));
+        assert_eq!(map.len(), 2);
+
+        map.insert('e', 4);
+        assert_eq!(map.last(), Some((&'e', &4)));
+        assert_eq!(map.get_index_of(&'e'), Some(2));
+    }
+
+    #[test]
+    fn entry_or_default_accumulates() {
+        let mut map: IndexMap<&str, Vec<i32>> = IndexMap::new();
+        for (key, value) in [("b", 1), ("a", 2), ("b", 3), ("c", 4), ("a", 5)] {
+            map.entry(key).or_default().push(value);
+        }
+
+        let keys: Vec<_> = map.keys().copied().collect();
+        assert_eq!(keys, vec!["b", "a", "c"]);
+        assert_eq!(map.get(&"b"), Some(&vec![1, 3]));
+        assert_eq!(map.get(&"a"), Some(&vec![2, 5]));
+        assert_eq!(map.get(&"c"), Some(&vec![4]));
+    }
+
+    #[test]
+    fn entry_variants() {
+        let mut map: IndexMap<char, i32> = "ab".chars().zip(0..).collect();
+
+        match map.entry('b') {
+            Entry::Occupied(entry) => assert_eq!(entry.index(), 1),
+            Entry::Vacant(_) => panic!("'b' should be occupied"),
+        }
+        match map.entry('z') {
+            Entry::Vacant(entry) => {
+                assert_eq!(*entry.key(), 'z');
+                assert_eq!(entry.index(), 2);
+                assert_eq!(*entry.or_default(), 0);
+            }
+            Entry::Occupied(_) => panic!("'z' should be vacant"),
+        }
+
+        *map.entry('a').and_modify(|v| *v += 10).or_insert(100) += 1;
+        *map.entry('y').and_modify(|v| *v += 10).or_insert(100) += 1;
+        assert_eq!(map.get(&'a'), Some(&11));
+        assert_eq!(map.get(&'y'), Some(&101));
+        assert_eq!(map.len(), 4);
+    }
+
+    #[test]
+    fn get_or_insert_default() {
+        let mut map: IndexMap<&str, u32> = IndexMap::new();
+        *map.get_or_insert_default("x") += 1;
+        *map.get_or_insert_default("x") += 1;
+        *map.get_or_insert_default("y") += 5;
+
+        assert_eq!(map.get(&"x"), Some(&2));
+        assert_eq!(map.get(&"y"), Some(&5));
+    }
+
+    #[test]
+    fn vacant_entry_replaces_colliding_key() {
+        use std::hash::BuildHasherDefault;
+
+        use crate::hasher::CollidingHasher;
+
+        type CollidingMap = super::IndexMap<u32, &'static str, BuildHasherDefault<CollidingHasher>>;
+
+        let mut map = CollidingMap::default();
+        map.entry(1).or_insert("a");
+
+        match map.entry(2) {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.index(), 0);
+                entry.insert("b");
+            }
+            Entry::Occupied(_) => panic!("2 should be vacant"),
+        }
+
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(&1), None);
+        assert_eq!(map.get(&2), Some(&"b"));
+        assert!(map.iter().eq([(&2, &"b")]));
+
+        assert_eq!(map.remove(&2), Some("b"));
+        assert!(map.is_empty());
+        assert_eq!(map.iter().count(), 0);
+    }
+
+    #[test]
+    fn entries_mutate_and_remove_in_one_pass() {
+        let mut map: IndexMap<char, i32> = "abcdef".chars().zip(0..).collect();
+        map.remove(&'b');
+
+        let expired = ['c', 'f'];
+        let mut entries = map.entries();
+        assert_eq!(entries.len(), 5);
+        while let Some(mut entry) = entries.next() {
+            if expired.contains(entry.key()) {
+                entry.remove();
+            } else {
+                *entry.get_mut() *= 10;
+            }
+        }
+        assert!(entries.is_empty());
+        drop(entries);
+
+        let pairs: Vec<_> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(pairs, vec![('a', 0), ('d', 30), ('e', 40)]);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.physical_len(), 3);
+        assert_eq!(map.get(&'c'), None);
+        assert_eq!(map.get_index_of(&'e'), Some(2));
+
+        let mut entries = map.entries();
+        while let Some(entry) = entries.next() {
+            if *entry.get() > 0 {
+                *entry.into_mut() += 1;
+            } else {
+                assert_eq!(entry.remove_entry(), ('a', 0));
+            }
+        }
+        drop(entries);
+
+        let pairs: Vec<_> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(pairs, vec![('d', 31), ('e', 41)]);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.tombstone_count(), 0);
+        assert_eq!(map.get_index_of(&'e'), Some(1));
+
+        map.insert('a', 5);
+        assert_eq!(map.get_index_of(&'a'), Some(2));
+    }
+
+    #[test]
+    fn vacant_insert_does_not_clone_the_key() {
+        use std::cell::Cell;
+
+        thread_local! {
+            static CLONES: Cell<usize> = const { Cell::new(0) };
+        }
+
+        #[derive(Debug, Hash, PartialEq, Eq)]
+        struct Key(u32);
+
+        impl Clone for Key {
+            fn clone(&self) -> Self {
+                CLONES.with(|clones| clones.set(clones.get() + 1));
+                Key(self.0)
+            }
+        }
+
+        let mut map: IndexMap<Key, &str> = IndexMap::new();
+        for i in 0..3 {
+            map.insert(Key(i), "old");
+        }
+        CLONES.with(|clones| clones.set(0));
+
+        match map.entry(Key(7)) {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), &Key(7));
+                entry.insert("new");
+            }
+            Entry::Occupied(_) => panic!("7 should be vacant"),
+        }
+        match map.entry(Key(8)) {
+            Entry::Vacant(entry) => assert_eq!(entry.into_key(), Key(8)),
+            Entry::Occupied(_) => panic!("8 should be vacant"),
+        }
+
+        assert_eq!(CLONES.with(Cell::get), 0);
+        assert_eq!(map.get(&Key(7)), Some(&"new"));
+        assert_eq!(map.get(&Key(8)), None);
+        assert_eq!(map.len(), 4);
+    }
+
+    #[test]
+    fn entry_ref_only_converts_missing_keys() {
+        use std::borrow::Borrow;
+        use std::cell::Cell;
+
+        thread_local! {
+            static OWNED: Cell<usize> = const { Cell::new(0) };
+        }
+
+        #[derive(Debug, Hash, PartialEq, Eq)]
+        struct Key(String);
+
+        impl Clone for Key {
+            fn clone(&self) -> Self {
+                OWNED.with(|owned| owned.set(owned.get() + 1));
+                Key(self.0.clone())
+            }
+        }
+
+        impl From<&str> for Key {
+            fn from(key: &str) -> Self {
+                OWNED.with(|owned| owned.set(owned.get() + 1));
+                Key(key.to_string())
+            }
+        }
+
+        impl Borrow<str> for Key {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        let mut counts: IndexMap<Key, u32> = IndexMap::new();
+        counts.insert(Key("a".to_string()), 1);
+        OWNED.with(|owned| owned.set(0));
+
+        *counts.entry_ref("a").or_default() += 1;
+        match counts.entry_ref("a") {
+            EntryByRef::Occupied(entry) => assert_eq!(entry.index(), 0),
+            EntryByRef::Vacant(_) => panic!("\"a\" should be occupied"),
+        }
+        assert_eq!(OWNED.with(Cell::get), 0);
+
+        match counts.entry_ref("b") {
+            EntryByRef::Vacant(entry) => {
+                assert_eq!(entry.key(), "b");
+                assert_eq!(entry.index(), 1);
+                assert_eq!(OWNED.with(Cell::get), 0);
+                *entry.insert(5) += 1;
+            }
+            EntryByRef::Occupied(_) => panic!("\"b\" should be vacant"),
+        }
+        assert_eq!(OWNED.with(Cell::get), 1);
+
+        counts.entry_ref("b").and_modify(|v| *v *= 2).or_insert(0);
+        assert_eq!(counts.entry_ref("c").key(), "c");
+        assert_eq!(OWNED.with(Cell::get), 1);
+
+        let pairs: Vec<_> = counts.iter().map(|(k, v)| (k.0.as_str(), *v)).collect();
+        assert_eq!(pairs, vec![("a", 2), ("b", 12)]);
+    }
+
+    #[test]
+    fn entries_of_empty_map() {
+        let mut map: IndexMap<char, i32> = IndexMap::new();
+        assert!(map.first_entry().is_none());
+        assert!(map.last_entry().is_none());
+
+        map.insert('a', 1);
+        map.remove(&'a');
+        assert!(map.first_entry().is_none());
+        assert!(map.last_entry().is_none());
+    }
+}
@@ -0,0 +1,177 @@
+use std::hash::{BuildHasher, Hash};
+
+use imbl::shared_ptr::SharedPointerKind;
+
+use super::{HashValue, IndexMap, OccupiedEntry, VacantEntry};
+
+/// A builder for looking up an entry of an [`IndexMap`] by a precomputed
+/// hash. Returned by [`IndexMap::raw_entry_mut`].
+pub struct RawEntryBuilderMut<'a, K, V, S, P: SharedPointerKind> {
+    map: &'a mut IndexMap<K, V, S, P>,
+}
+
+impl<'a, K, V, S, P: SharedPointerKind> RawEntryBuilderMut<'a, K, V, S, P> {
+    pub(super) fn new(map: &'a mut IndexMap<K, V, S, P>) -> Self {
+        Self { map }
+    }
+}
+
+impl<'a, K, V, S, P: SharedPointerKind> RawEntryBuilderMut<'a, K, V, S, P>
+where
+    S: BuildHasher,
+{
+    /// Looks up the entry whose key hashes to `hash` and for which
+    /// `is_match` returns `true`.
+    ///
+    /// `hash` must be the hash of the key under the map's hasher, as
+    /// returned by `map.hasher().hash_one(&key)`.
+    pub fn from_hash<F>(self, hash: u64, mut is_match: F) -> RawEntryMut<'a, K, V, S, P>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let hash = HashValue(hash);
+        let slot = self.map.indices.get(&hash).copied().filter(|&slot| {
+            self.map.entries[slot]
+                .as_ref()
+                .is_some_and(|bucket| is_match(&bucket.key))
+        });
+
+        match slot {
+            Some(slot) => RawEntryMut::Occupied(OccupiedEntry::new(self.map, slot)),
+            None => RawEntryMut::Vacant(RawVacantEntryMut {
+                map: self.map,
+                hash,
+            }),
+        }
+    }
+}
+
+/// A view into a single entry of an [`IndexMap`] found by a precomputed
+/// hash, which may be vacant or occupied.
+pub enum RawEntryMut<'a, K, V, S, P: SharedPointerKind> {
+    Occupied(OccupiedEntry<'a, K, V, S, P>),
+    Vacant(RawVacantEntryMut<'a, K, V, S, P>),
+}
+
+/// A handle to a missing entry of an [`IndexMap`], holding the hash its key
+/// will be stored under.
+pub struct RawVacantEntryMut<'a, K, V, S, P: SharedPointerKind> {
+    map: &'a mut IndexMap<K, V, S, P>,
+    hash: HashValue,
+}
+
+impl<'a, K, V, S, P: SharedPointerKind> RawVacantEntryMut<'a, K, V, S, P> {
+    /// Returns the logical position the entry would be inserted at.
+    pub fn index(&self) -> usize
+    where
+        K: Hash + Eq,
+        S: BuildHasher,
+    {
+        self.map.vacant_index(self.hash)
+    }
+}
+
+impl<'a, K, V, S, P: SharedPointerKind> RawVacantEntryMut<'a, K, V, S, P>
+where
+    K: Clone + Hash + Eq,
+    V: Clone,
+    S: Clone + BuildHasher,
+{
+    /// Inserts `key` and `value` at the end of the map under the hash given
+    /// to [`RawEntryBuilderMut::from_hash`], returning a mutable reference
+    /// to the value.
+    ///
+    /// Only one entry is kept per hash, so if the lookup found an entry with
+    /// this hash but `is_match` rejected it, that entry is replaced in place
+    /// rather than duplicated, as with [`IndexMap::insert`].
+    pub fn insert(self, key: K, value: V) -> &'a mut V {
+        debug_assert_eq!(
+            self.map.hash(&key),
+            self.hash,
+            "RawVacantEntryMut::insert: hash does not match the key"
+        );
+        VacantEntry::new(self.map, self.hash, key).insert(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::{BuildHasher, RandomState};
+
+    use super::RawEntryMut;
+
+    type IndexMap<K, V> = super::IndexMap<K, V, RandomState>;
+
+    #[test]
+    fn raw_entry_insert_and_lookup() {
+        let mut map: IndexMap<String, usize> = IndexMap::new();
+        let mut interned = Vec::new();
+
+        for word in ["b", "a", "b", "c", "a"] {
+            let hash = map.hasher().hash_one(word);
+            let id = match map.raw_entry_mut().from_hash(hash, |k| k == word) {
+                RawEntryMut::Occupied(entry) => *entry.get(),
+                RawEntryMut::Vacant(entry) => {
+                    let id = entry.index();
+                    *entry.insert(word.to_string(), id)
+                }
+            };
+            interned.push(id);
+        }
+
+        assert_eq!(interned, vec![0, 1, 0, 2, 1]);
+        let keys: Vec<_> = map.keys().cloned().collect();
+        assert_eq!(keys, vec!["b", "a", "c"]);
+        assert_eq!(map.get(&"c".to_string()), Some(&2));
+        assert_eq!(map.get_index_of(&"a".to_string()), Some(1));
+    }
+
+    #[test]
+    fn raw_entry_occupied_handle() {
+        let mut map: IndexMap<String, usize> = IndexMap::new();
+        map.insert("x".to_string(), 1);
+        map.insert("y".to_string(), 2);
+
+        let hash = map.hasher().hash_one("x");
+        match map.raw_entry_mut().from_hash(hash, |k| k == "x") {
+            RawEntryMut::Occupied(mut entry) => {
+                assert_eq!(entry.key(), "x");
+                *entry.get_mut() += 10;
+            }
+            RawEntryMut::Vacant(_) => panic!("\"x\" should be occupied"),
+        }
+        assert_eq!(map.get(&"x".to_string()), Some(&11));
+
+        match map.raw_entry_mut().from_hash(hash, |k| k == "x") {
+            RawEntryMut::Occupied(entry) => assert_eq!(entry.remove(), 11),
+            RawEntryMut::Vacant(_) => panic!("\"x\" should be occupied"),
+        }
+        assert_eq!(map.get(&"x".to_string()), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn raw_entry_rejected_match_replaces_entry() {
+        let mut map: IndexMap<String, usize> = IndexMap::new();
+        map.insert("w".to_string(), 0);
+        map.insert("x".to_string(), 1);
+
+        let hash = map.hasher().hash_one("x");
+        match map.raw_entry_mut().from_hash(hash, |_| false) {
+            RawEntryMut::Vacant(entry) => {
+                assert_eq!(entry.index(), 1);
+                assert_eq!(*entry.insert("x".to_string(), 2), 2);
+            }
+            RawEntryMut::Occupied(_) => panic!("the lookup should be vacant"),
+        }
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&"x".to_string()), Some(&2));
+        let pairs: Vec<_> = map.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(pairs, vec![("w", 0), ("x", 2)]);
+
+        assert_eq!(map.remove(&"x".to_string()), Some(2));
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.iter().count(), 1);
+    }
+}
@@ -0,0 +1,69 @@
+use imbl::shared_ptr::SharedPointerKind;
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+use crate::{IndexMap, IndexSet};
+
+impl<K, V, S, P: SharedPointerKind> IndexMap<K, V, S, P>
+where
+    K: Sync,
+    V: Sync,
+{
+    /// Returns a parallel iterator over the live entries, in order.
+    ///
+    /// With the default `RcK` pointer kind the map's nodes cannot be handed
+    /// to other threads, so the entries are first collected into a vector of
+    /// references which rayon then splits across its workers. Use
+    /// [`ArcIndexMap`](crate::ArcIndexMap) to share the map itself.
+    ///
+    /// That collection happens on every call, on the calling thread, before
+    /// any parallel work starts: it takes O(n) time and allocates a vector
+    /// of `n` references. It only pays off when the work done per entry
+    /// outweighs it; [`IndexMap::par_keys`] and [`IndexMap::par_values`] have
+    /// the same cost.
+    pub fn par_iter(&self) -> impl IndexedParallelIterator<Item = (&K, &V)> {
+        self.iter().collect::<Vec<_>>().into_par_iter()
+    }
+
+    pub fn par_keys(&self) -> impl IndexedParallelIterator<Item = &K> {
+        self.par_iter().map(|(k, _)| k)
+    }
+
+    pub fn par_values(&self) -> impl IndexedParallelIterator<Item = &V> {
+        self.par_iter().map(|(_, v)| v)
+    }
+}
+
+impl<T, S, P: SharedPointerKind> IndexSet<T, S, P>
+where
+    T: Sync,
+{
+    /// Returns a parallel iterator over the live items, in order.
+    ///
+    /// See [`IndexMap::par_iter`] for how the items are distributed: they
+    /// are collected into a vector of references first, in O(n) time on the
+    /// calling thread.
+    pub fn par_iter(&self) -> impl IndexedParallelIterator<Item = &T> {
+        self.iter().collect::<Vec<_>>().into_par_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::iter::ParallelIterator;
+
+    use crate::{IndexMap, IndexSet};
+
+    #[test]
+    fn par_sum() {
+        let map: IndexMap<u64, u64> = (0..10_000).map(|i| (i, i * 2)).collect();
+        let map = map.without(&10).without(&20);
+
+        let expected: u64 = map.values().sum();
+        assert_eq!(map.par_values().sum::<u64>(), expected);
+        assert_eq!(map.par_keys().sum::<u64>(), 49_995_000 - 30);
+        assert_eq!(map.par_iter().count(), map.len());
+
+        let set: IndexSet<u64> = (0..1000).collect();
+        assert_eq!(set.par_iter().sum::<u64>(), 499_500);
+    }
+}
@@ -0,0 +1,167 @@
+use core::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+
+use imbl::shared_ptr::SharedPointerKind;
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::{IndexMap, IndexSet};
+
+impl<K, V, S, P: SharedPointerKind> Serialize for IndexMap<K, V, S, P>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: Serializer,
+    {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<T, S, P: SharedPointerKind> Serialize for IndexSet<T, S, P>
+where
+    T: Serialize,
+{
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: Serializer,
+    {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+struct MapVisitor<K, V, S, P: SharedPointerKind> {
+    marker: PhantomData<IndexMap<K, V, S, P>>,
+}
+
+impl<'de, K, V, S, P: SharedPointerKind> Visitor<'de> for MapVisitor<K, V, S, P>
+where
+    K: Deserialize<'de> + Clone + Hash + Eq,
+    V: Deserialize<'de> + Clone,
+    S: Clone + Default + BuildHasher,
+{
+    type Value = IndexMap<K, V, S, P>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut map = IndexMap::new();
+        while let Some((key, value)) = access.next_entry()? {
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
+impl<'de, K, V, S, P: SharedPointerKind> Deserialize<'de> for IndexMap<K, V, S, P>
+where
+    K: Deserialize<'de> + Clone + Hash + Eq,
+    V: Deserialize<'de> + Clone,
+    S: Clone + Default + BuildHasher,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(MapVisitor {
+            marker: PhantomData,
+        })
+    }
+}
+
+struct SetVisitor<T, S, P: SharedPointerKind> {
+    marker: PhantomData<IndexSet<T, S, P>>,
+}
+
+impl<'de, T, S, P: SharedPointerKind> Visitor<'de> for SetVisitor<T, S, P>
+where
+    T: Deserialize<'de> + Clone + Hash + Eq,
+    S: Clone + Default + BuildHasher,
+{
+    type Value = IndexSet<T, S, P>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a sequence")
+    }
+
+    fn visit_seq<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut set = IndexSet::new();
+        while let Some(item) = access.next_element()? {
+            set.insert(item);
+        }
+        Ok(set)
+    }
+}
+
+impl<'de, T, S, P: SharedPointerKind> Deserialize<'de> for IndexSet<T, S, P>
+where
+    T: Deserialize<'de> + Clone + Hash + Eq,
+    S: Clone + Default + BuildHasher,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(SetVisitor {
+            marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{IndexMap, IndexSet};
+
+    #[test]
+    fn map_round_trip_preserves_order() {
+        let map: IndexMap<String, i32> = [("c", 3), ("a", 1), ("b", 2)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(json, r#"{"c":3,"a":1,"b":2}"#);
+
+        let back: IndexMap<String, i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, map);
+        assert!(back.iter().eq(map.iter()));
+    }
+
+    #[test]
+    fn map_round_trip_skips_removed_entries() {
+        let map: IndexMap<i32, String> = (0..5).map(|i| (i, i.to_string())).collect();
+        let map = map.without(&0).without(&3);
+
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(json, r#"{"1":"1","2":"2","4":"4"}"#);
+
+        let back: IndexMap<i32, String> = serde_json::from_str(&json).unwrap();
+        let keys: Vec<_> = back.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![1, 2, 4]);
+        assert_eq!(back.get(&4), Some(&"4".to_string()));
+    }
+
+    #[test]
+    fn set_round_trip_preserves_order() {
+        let set: IndexSet<i32> = [5, 1, 4, 2].into_iter().collect();
+        let set = set.without(&4);
+
+        let json = serde_json::to_string(&set).unwrap();
+        assert_eq!(json, "[5,1,2]");
+
+        let back: IndexSet<i32> = serde_json::from_str(&json).unwrap();
+        let items: Vec<_> = back.iter().copied().collect();
+        assert_eq!(items, vec![5, 1, 2]);
+    }
+}
@@ -1,13 +1,24 @@
 use core::fmt;
-use std::hash::{BuildHasher, Hash, RandomState};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::hash::{BuildHasher, Hash, Hasher, RandomState};
+use std::iter::FusedIterator;
+
+use imbl::shared_ptr::{ArcK, RcK, SharedPointerKind};
 
 use crate::map::IndexMap;
+use crate::Equivalent;
+
+/// An [`IndexSet`] backed by atomically reference counted nodes, which can be
+/// sent to and shared between threads.
+pub type ArcIndexSet<T, S = RandomState> = IndexSet<T, S, ArcK>;
 
-pub struct IndexSet<T, S = RandomState> {
-    map: IndexMap<T, (), S>,
+pub struct IndexSet<T, S = RandomState, P: SharedPointerKind = RcK> {
+    map: IndexMap<T, (), S, P>,
 }
 
-impl<T, S> fmt::Debug for IndexSet<T, S>
+/// Formats the live items in iteration order.
+impl<T, S, P: SharedPointerKind> fmt::Debug for IndexSet<T, S, P>
 where
     T: fmt::Debug,
 {
@@ -16,7 +27,7 @@ where
     }
 }
 
-impl<T, S> IndexSet<T, S>
+impl<T, S, P: SharedPointerKind> IndexSet<T, S, P>
 where
     S: Clone + Default,
 {
@@ -25,14 +36,26 @@ where
         Self::with_hasher(S::default())
     }
 
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, S::default())
+    }
+
     pub fn new_from(&self) -> Self {
         Self {
             map: self.map.new_from(),
         }
     }
+
+    /// See [`IndexMap::new_from_with_capacity`].
+    pub fn new_from_with_capacity(&self) -> Self {
+        Self {
+            map: self.map.new_from_with_capacity(),
+        }
+    }
 }
 
-impl<T, S> Default for IndexSet<T, S>
+impl<T, S, P: SharedPointerKind> Default for IndexSet<T, S, P>
 where
     S: Clone + Default,
 {
@@ -42,7 +65,11 @@ where
     }
 }
 
-impl<T, S> IndexSet<T, S> {
+impl<T, S, P: SharedPointerKind> IndexSet<T, S, P> {
+    pub(crate) fn from_map(map: IndexMap<T, (), S, P>) -> Self {
+        Self { map }
+    }
+
     #[inline]
     pub fn with_hasher(hash_builder: S) -> Self
     where
@@ -54,12 +81,95 @@ impl<T, S> IndexSet<T, S> {
     }
 
     #[inline]
-    pub fn iter(&self) -> impl Iterator<Item = &T> {
-        self.map.iter().map(|(k, _)| k)
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self
+    where
+        S: Clone,
+    {
+        Self {
+            map: IndexMap::with_capacity_and_hasher(capacity, hash_builder),
+        }
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    /// Returns the live items, in order, as a persistent vector.
+    ///
+    /// See [`IndexMap::to_vector`] for the cost.
+    pub fn to_vector(&self) -> imbl::GenericVector<T, P>
+    where
+        T: Clone,
+    {
+        self.iter().cloned().collect()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, T, P> {
+        Iter {
+            inner: self.map.iter(),
+        }
+    }
+
+    /// Returns a map from each item to the value built for it by `f`, in the
+    /// same order and with the same hasher. Items are not rehashed: the map
+    /// shares its index with this set.
+    pub fn into_map_with<V, F>(&self, mut f: F) -> IndexMap<T, V, S, P>
+    where
+        T: Clone,
+        V: Clone,
+        S: Clone,
+        F: FnMut(&T) -> V,
+    {
+        self.map.map_entries(|item, _| f(item))
+    }
+
+    /// Returns references to the live items, in order.
+    pub fn as_slice_vec(&self) -> Vec<&T> {
+        self.iter().collect()
+    }
+
+    /// Returns the number of live items for which `f` returns `true`.
+    pub fn count_if<F>(&self, mut f: F) -> usize
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.map.count_if(|item, _| f(item))
+    }
+
+    /// Returns `true` if `f` returns `true` for any live item, stopping at
+    /// the first one that does.
+    pub fn any<F>(&self, mut f: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.map.any(|item, _| f(item))
+    }
+
+    /// Returns `true` if `f` returns `true` for every live item, stopping at
+    /// the first one that does not.
+    pub fn all<F>(&self, mut f: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.map.all(|item, _| f(item))
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<&T> {
+        self.map.get_index(index).map(|(item, _)| item)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.map.first().map(|(item, _)| item)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.map.last().map(|(item, _)| item)
     }
 }
 
-impl<T, S> Clone for IndexSet<T, S>
+impl<T, S, P: SharedPointerKind> Clone for IndexSet<T, S, P>
 where
     T: Clone,
     S: Clone,
@@ -75,24 +185,49 @@ where
     }
 }
 
-impl<T, S> PartialEq for IndexSet<T, S>
+/// Two sets are equal when they hold equal items in the same order.
+impl<T, S, P: SharedPointerKind> PartialEq for IndexSet<T, S, P>
 where
-    T: Hash + Eq,
-    S: BuildHasher,
+    T: PartialEq,
 {
     fn eq(&self, other: &Self) -> bool {
         self.map == other.map
     }
 }
 
-impl<T, S> Eq for IndexSet<T, S>
+impl<T, S, P: SharedPointerKind> Eq for IndexSet<T, S, P> where T: Eq {}
+
+/// Sets are compared lexicographically by their items in iteration order.
+impl<T, S, P: SharedPointerKind> PartialOrd for IndexSet<T, S, P>
+where
+    T: PartialOrd,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T, S, P: SharedPointerKind> Ord for IndexSet<T, S, P>
 where
-    T: Hash + Eq,
-    S: BuildHasher,
+    T: Ord,
 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
 }
 
-impl<T, S> FromIterator<T> for IndexSet<T, S>
+/// Sets are hashed in iteration order, consistent with their [`PartialEq`]
+/// implementation.
+impl<T, S, P: SharedPointerKind> Hash for IndexSet<T, S, P>
+where
+    T: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.map.hash(state);
+    }
+}
+
+impl<T, S, P: SharedPointerKind> FromIterator<T> for IndexSet<T, S, P>
 where
     S: Clone + Default + BuildHasher,
     T: Clone + Hash + Eq,
@@ -103,13 +238,41 @@ where
     {
         let mut set = Self::new();
         for item in iter {
-            set = set.update(item);
+            set.insert(item);
         }
         set
     }
 }
 
-impl<T, S> IndexSet<T, S>
+impl<T, S, P: SharedPointerKind> Extend<T> for IndexSet<T, S, P>
+where
+    S: Clone + BuildHasher,
+    T: Clone + Hash + Eq,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+impl<'a, T, S, P: SharedPointerKind> Extend<&'a T> for IndexSet<T, S, P>
+where
+    S: Clone + BuildHasher,
+    T: Copy + Hash + Eq,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = &'a T>,
+    {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl<T, S, P: SharedPointerKind> IndexSet<T, S, P>
 where
     T: Hash + Eq,
     S: BuildHasher,
@@ -118,6 +281,29 @@ where
         self.map.contains_key(item)
     }
 
+    pub fn get_index_of(&self, item: &T) -> Option<usize> {
+        self.map.get_index_of(item)
+    }
+
+    /// Like [`IndexSet::contains`], but takes any type that is
+    /// [`Equivalent`] to the item.
+    pub fn contains_equivalent<Q>(&self, item: &Q) -> bool
+    where
+        Q: Hash + Equivalent<T> + ?Sized,
+    {
+        self.map.contains_equivalent(item)
+    }
+
+    /// Like [`IndexSet::contains`], but takes any borrowed form of the item,
+    /// such as a `&str` for a `String` item.
+    pub fn contains_by<Q>(&self, item: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key_by(item)
+    }
+
     pub fn len(&self) -> usize {
         self.map.len()
     }
@@ -125,15 +311,42 @@ where
     pub fn is_empty(&self) -> bool {
         self.map.is_empty()
     }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.len() <= other.len() && self.iter().all(|item| other.contains(item))
+    }
+
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        let (small, large) = if self.len() <= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        !small.iter().any(|item| large.contains(item))
+    }
 }
 
-impl<T, S> IndexSet<T, S>
+impl<T, S, P: SharedPointerKind> IndexSet<T, S, P>
 where
     T: Clone + Hash + Eq,
     S: Clone + BuildHasher,
 {
-    pub fn insert(&mut self, item: T) {
-        self.map.insert(item, ())
+    /// Inserts `item`, returning whether it was newly added.
+    pub fn insert(&mut self, item: T) -> bool {
+        self.map.insert(item, ()).is_none()
+    }
+
+    /// Returns the stored item equal to `item`, inserting the one built by
+    /// `f` if there is none.
+    pub fn get_or_insert_with<F>(&mut self, item: &T, f: F) -> &T
+    where
+        F: FnOnce(&T) -> T,
+    {
+        self.map.get_or_insert_key_with(item, f, ())
     }
 
     pub fn update(&self, item: T) -> Self {
@@ -148,71 +361,159 @@ where
         }
     }
 
+    /// Like [`IndexSet::without`], but takes any borrowed form of the item.
+    pub fn without_by<Q>(&self, item: &Q) -> Self
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Self {
+            map: self.map.without_by(item),
+        }
+    }
+
+    /// Removes `item`, leaving the positions of the remaining items
+    /// unaffected.
     pub fn remove(&mut self, item: &T) {
         self.map.remove(item);
     }
 
-    pub fn is_subset(&self, other: &Self) -> bool {
-        self.iter().all(|item| other.contains(item))
+    /// Removes `item` and shifts every following item down by one position,
+    /// returning whether it was present.
+    ///
+    /// Time: O(n)
+    pub fn shift_remove(&mut self, item: &T) -> bool {
+        self.map.shift_remove(item).is_some()
     }
 
-    pub fn union(self, other: Self) -> Self
+    /// Removes `item` and moves the last item into its position, returning
+    /// whether it was present. This does not preserve the order of the
+    /// remaining items.
+    pub fn swap_remove(&mut self, item: &T) -> bool {
+        self.map.swap_remove(item).is_some()
+    }
+
+    /// Returns a new set without `item`, with every following item shifted
+    /// down by one position, and whether `item` was present.
+    ///
+    /// Time: O(n)
+    pub fn shift_without(&self, item: &T) -> (Self, bool) {
+        let mut out = self.clone();
+        let removed = out.shift_remove(item);
+        (out, removed)
+    }
+
+    /// Returns a new set without `item`, with the last item moved into its
+    /// position, and whether `item` was present. This does not preserve the
+    /// order of the remaining items.
+    pub fn swap_without(&self, item: &T) -> (Self, bool) {
+        let mut out = self.clone();
+        let removed = out.swap_remove(item);
+        (out, removed)
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    pub fn retain<F>(&mut self, mut f: F)
     where
-        S: Default,
+        F: FnMut(&T) -> bool,
     {
-        let (mut out, from) = if self.len() >= other.len() {
-            (self, other)
-        } else {
-            (other, self)
-        };
+        self.map.retain(|item, _| f(item));
+    }
 
-        for value in from {
-            out.insert(value);
-        }
+    /// Returns a new set holding the items for which `f` returns `true`, in
+    /// their original order.
+    pub fn filter<F>(&self, f: F) -> Self
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut out = self.clone();
+        out.retain(f);
         out
     }
 
-    pub fn intersection(self, other: Self) -> Self
+    /// Returns a new set holding `f` applied to each item, in order, with
+    /// the same hasher.
+    ///
+    /// If several items map to equal values, the value is kept once, at the
+    /// position of the first of them.
+    pub fn map<U, F>(&self, mut f: F) -> IndexSet<U, S, P>
     where
-        S: Default,
+        U: Clone + Hash + Eq,
+        F: FnMut(&T) -> U,
     {
-        let mut out = self.new_from();
-        for elem in other {
-            if self.contains(&elem) {
-                out.insert(elem);
-            }
+        let mut out = IndexSet::with_hasher(self.map.hasher().clone());
+        for item in self.iter() {
+            out.insert(f(item));
         }
         out
     }
 
-    pub fn relative_complement(mut self, other: Self) -> Self
+    /// Splits the set into the items for which `f` returns `true` and those
+    /// for which it returns `false`, both in their original order.
+    pub fn partition<F>(&self, mut f: F) -> (Self, Self)
     where
-        S: Default,
+        F: FnMut(&T) -> bool,
     {
-        for value in other {
-            self.remove(&value);
+        let (matching, rest) = self.map.partition(|item, _| f(item));
+        (Self { map: matching }, Self { map: rest })
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut out = self.clone();
+        for item in other.iter() {
+            if !self.contains(item) {
+                out.insert(item.clone());
+            }
         }
-        self
+        out
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut out = self.clone();
+        out.retain(|item| other.contains(item));
+        out
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut out = self.clone();
+        out.retain(|item| !other.contains(item));
+        out
+    }
+
+    #[deprecated(note = "use `IndexSet::difference` instead")]
+    pub fn relative_complement(self, other: Self) -> Self {
+        self.difference(&other)
+    }
+
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        let mut out = self.difference(other);
+        for item in other.iter() {
+            if !self.contains(item) {
+                out.insert(item.clone());
+            }
+        }
+        out
     }
 }
 
-impl<'a, T, S> Iterator for &'a IndexSet<T, S>
-where
-    T: Clone,
-{
+impl<'a, T, S, P: SharedPointerKind> IntoIterator for &'a IndexSet<T, S, P> {
     type Item = &'a T;
+    type IntoIter = Iter<'a, T, P>;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.iter().next()
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
 }
 
-impl<T, S> IntoIterator for IndexSet<T, S>
+impl<T, S, P: SharedPointerKind> IntoIterator for IndexSet<T, S, P>
 where
     T: Clone,
 {
     type Item = T;
-    type IntoIter = IntoIter<T>;
+    type IntoIter = IntoIter<T, P>;
 
     fn into_iter(self) -> Self::IntoIter {
         IntoIter {
@@ -221,11 +522,11 @@ where
     }
 }
 
-pub struct IntoIter<T> {
-    inner: crate::map::IntoIter<T, ()>,
+pub struct IntoIter<T, P: SharedPointerKind = RcK> {
+    inner: crate::map::IntoIter<T, (), P>,
 }
 
-impl<T> Iterator for IntoIter<T>
+impl<T, P: SharedPointerKind> Iterator for IntoIter<T, P>
 where
     T: Clone,
 {
@@ -234,8 +535,51 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         self.inner.next().map(|(k, _)| k)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
 }
 
+impl<T, P: SharedPointerKind> DoubleEndedIterator for IntoIter<T, P>
+where
+    T: Clone,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<T, P: SharedPointerKind> ExactSizeIterator for IntoIter<T, P> where T: Clone {}
+
+impl<T, P: SharedPointerKind> FusedIterator for IntoIter<T, P> where T: Clone {}
+
+pub struct Iter<'a, T, P: SharedPointerKind = RcK> {
+    inner: crate::map::Iter<'a, T, (), P>,
+}
+
+impl<'a, T, P: SharedPointerKind + 'a> Iterator for Iter<'a, T, P> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T, P: SharedPointerKind + 'a> DoubleEndedIterator for Iter<'a, T, P> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<'a, T, P: SharedPointerKind + 'a> ExactSizeIterator for Iter<'a, T, P> {}
+
+impl<'a, T, P: SharedPointerKind + 'a> FusedIterator for Iter<'a, T, P> {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -371,4 +715,470 @@ mod tests {
             assert!(set.contains(&i));
         }
     }
+
+    fn items(set: &IndexSet<i32>) -> Vec<i32> {
+        set.iter().copied().collect()
+    }
+
+    #[test]
+    fn iter_rev() {
+        let set: IndexSet<i32> = (0..5).collect();
+        let set = set.without(&2);
+
+        assert_eq!(
+            set.iter().rev().copied().collect::<Vec<_>>(),
+            vec![4, 3, 1, 0]
+        );
+        assert_eq!(set.into_iter().rev().collect::<Vec<_>>(), vec![4, 3, 1, 0]);
+    }
+
+    #[test]
+    fn iter_len_after_removal() {
+        let set: IndexSet<i32> = (0..5).collect();
+        let set = set.without(&2).without(&4);
+
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.iter().len(), 3);
+        assert_eq!(set.into_iter().len(), 3);
+    }
+
+    #[test]
+    fn for_loop_over_borrowed_set() {
+        let set: IndexSet<i32> = [3, 1, 2].into_iter().collect();
+
+        let mut seen = Vec::new();
+        for item in &set {
+            seen.push(*item);
+        }
+        assert_eq!(seen, vec![3, 1, 2]);
+    }
+
+    #[test]
+    fn sets_as_hash_set_elements() {
+        use std::collections::HashSet;
+
+        let a: IndexSet<i32> = [1, 2, 3].into_iter().collect();
+        let b: IndexSet<i32> = [3, 2, 1].into_iter().collect();
+        let c: IndexSet<i32> = [1, 2].into_iter().collect();
+
+        let outer: HashSet<_> = [a.clone(), a.clone(), b, c].into_iter().collect();
+        assert_eq!(outer.len(), 3);
+        assert!(outer.contains(&a));
+    }
+
+    #[test]
+    fn insert_reports_new_items() {
+        let mut set: IndexSet<i32> = IndexSet::new();
+        assert!(set.insert(1));
+        assert!(set.insert(2));
+        assert!(!set.insert(1));
+        assert_eq!(items(&set), vec![1, 2]);
+    }
+
+    #[test]
+    fn build_with_insert_matches_update() {
+        let mut inserted: IndexSet<u32> = IndexSet::new();
+        let mut updated: IndexSet<u32> = IndexSet::new();
+        for i in 0..1000 {
+            let item = (i * 7919) % 1000;
+            assert!(inserted.insert(item));
+            updated = updated.update(item);
+        }
+
+        assert!(!inserted.insert(0));
+        assert_eq!(inserted.len(), 1000);
+        assert_eq!(inserted, updated);
+        assert_eq!(inserted, (0..1000).map(|i| (i * 7919) % 1000).collect());
+    }
+
+    #[test]
+    fn extend_from_iterator() {
+        let mut set: IndexSet<i32> = [3, 1].into_iter().collect();
+        set.extend(vec![2, 3, 4]);
+        assert_eq!(items(&set), vec![3, 1, 2, 4]);
+    }
+
+    #[test]
+    fn extend_from_references() {
+        let mut set: IndexSet<i32> = IndexSet::new();
+        let source = [5, 6, 5, 7];
+        set.extend(source.iter());
+        set.extend(&[8, 6]);
+        assert_eq!(items(&set), vec![5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn subset_and_superset() {
+        let small: IndexSet<i32> = [2, 1].into_iter().collect();
+        let large: IndexSet<i32> = [1, 2, 3].into_iter().collect();
+
+        assert!(small.is_subset(&large));
+        assert!(!large.is_subset(&small));
+        assert!(large.is_superset(&small));
+        assert!(!small.is_superset(&large));
+
+        assert!(large.is_subset(&large));
+        assert!(large.is_superset(&large));
+    }
+
+    #[test]
+    fn disjoint() {
+        let a: IndexSet<i32> = [1, 2].into_iter().collect();
+        let b: IndexSet<i32> = [3, 4, 5].into_iter().collect();
+        let c: IndexSet<i32> = [5, 2].into_iter().collect();
+
+        assert!(a.is_disjoint(&b));
+        assert!(b.is_disjoint(&a));
+        assert!(!a.is_disjoint(&c));
+        assert!(!b.is_disjoint(&c));
+    }
+
+    #[test]
+    fn empty_set_relations() {
+        let empty: IndexSet<i32> = IndexSet::new();
+        let set: IndexSet<i32> = [1, 2].into_iter().collect();
+
+        assert!(empty.is_subset(&set));
+        assert!(empty.is_subset(&empty));
+        assert!(set.is_superset(&empty));
+        assert!(!set.is_subset(&empty));
+        assert!(empty.is_disjoint(&set));
+        assert!(set.is_disjoint(&empty));
+        assert!(empty.is_disjoint(&empty));
+    }
+
+    #[test]
+    fn clear_and_reuse() {
+        let mut set: IndexSet<i32> = (0..10).collect();
+        set.clear();
+        assert!(set.is_empty());
+        assert!(!set.contains(&3));
+
+        set.insert(3);
+        assert_eq!(items(&set), vec![3]);
+    }
+
+    #[test]
+    fn retain_in_place() {
+        let mut set: IndexSet<i32> = (0..10).collect();
+        set.retain(|item| item % 3 == 0);
+        assert_eq!(items(&set), vec![0, 3, 6, 9]);
+        assert!(!set.contains(&1));
+    }
+
+    #[test]
+    fn filter_evens() {
+        let set: IndexSet<i32> = (0..100).rev().collect();
+        let set = set.without(&50);
+
+        let evens = set.filter(|item| item % 2 == 0);
+        assert_eq!(evens.len(), 49);
+        assert_eq!(
+            items(&evens),
+            (0..100)
+                .rev()
+                .filter(|i| i % 2 == 0 && *i != 50)
+                .collect::<Vec<_>>()
+        );
+        assert!(evens.contains(&98));
+        assert!(!evens.contains(&97));
+        assert!(!evens.contains(&50));
+        assert_eq!(evens.iter().len(), 49);
+
+        assert_eq!(set.len(), 99);
+        assert!(set.contains(&97));
+    }
+
+    #[test]
+    fn get_or_insert_with_interns() {
+        #[derive(Clone, Debug)]
+        struct Symbol {
+            name: String,
+            id: u32,
+        }
+
+        impl PartialEq for Symbol {
+            fn eq(&self, other: &Self) -> bool {
+                self.name == other.name
+            }
+        }
+
+        impl Eq for Symbol {}
+
+        impl Hash for Symbol {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.name.hash(state);
+            }
+        }
+
+        let symbol = |name: &str, id| Symbol {
+            name: name.to_string(),
+            id,
+        };
+
+        let mut set: IndexSet<Symbol> = IndexSet::new();
+        assert_eq!(set.get_or_insert_with(&symbol("a", 1), Clone::clone).id, 1);
+        assert_eq!(set.get_or_insert_with(&symbol("a", 2), Clone::clone).id, 1);
+        assert_eq!(set.get_or_insert_with(&symbol("b", 3), Clone::clone).id, 3);
+        assert_eq!(set.len(), 2);
+
+        set.remove(&symbol("a", 0));
+        assert_eq!(set.get_or_insert_with(&symbol("b", 4), Clone::clone).id, 3);
+        assert_eq!(set.get_or_insert_with(&symbol("c", 5), Clone::clone).id, 5);
+        assert_eq!(set.get_index(1).map(|s| s.id), Some(5));
+    }
+
+    #[test]
+    fn debug_after_removal() {
+        let set: IndexSet<i32> = [3, 1, 2].into_iter().collect();
+        let set = set.without(&1);
+
+        assert_eq!(format!("{set:?}"), "{3, 2}");
+        assert_eq!(format!("{set:#?}"), "{\n    3,\n    2,\n}");
+    }
+
+    #[test]
+    fn partition() {
+        let set: IndexSet<i32> = [7, 2, 9, 4, 3].into_iter().collect();
+
+        let (small, large) = set.partition(|item| *item < 5);
+        assert_eq!(items(&small), vec![2, 4, 3]);
+        assert_eq!(items(&large), vec![7, 9]);
+        assert!(small.is_disjoint(&large));
+        assert_eq!(small.union(&large).len(), set.len());
+    }
+
+    #[test]
+    fn ord_is_lexicographic() {
+        let set = |items: &[i32]| items.iter().copied().collect::<IndexSet<_>>();
+
+        assert!(set(&[1, 2, 3]) < set(&[1, 2, 4]));
+        assert!(set(&[2]) > set(&[1, 9]));
+        assert!(set(&[1, 2]) < set(&[1, 2, 3]));
+        assert_eq!(
+            set(&[3, 1]).cmp(&set(&[3, 2, 1]).without(&2)),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn borrowed_item_lookup() {
+        let set: IndexSet<String> = ["red", "green", "blue"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        assert!(set.contains_by("green"));
+        assert!(!set.contains_by("yellow"));
+
+        let set = set.without_by("green");
+        assert!(!set.contains_by("green"));
+        let items: Vec<_> = set.iter().map(String::as_str).collect();
+        assert_eq!(items, vec!["red", "blue"]);
+        assert_eq!(set.without_by("yellow").len(), 2);
+    }
+
+    #[test]
+    fn positional_access() {
+        let set: IndexSet<i32> = [10, 20, 30, 40].into_iter().collect();
+        let set = set.without(&20);
+
+        assert_eq!(set.get_index(0), Some(&10));
+        assert_eq!(set.get_index(1), Some(&30));
+        assert_eq!(set.get_index(2), Some(&40));
+        assert_eq!(set.get_index(3), None);
+        assert_eq!(set.get_index_of(&30), Some(1));
+        assert_eq!(set.get_index_of(&20), None);
+        assert_eq!(set.first(), Some(&10));
+        assert_eq!(set.last(), Some(&40));
+
+        let set = set.without(&10).without(&40);
+        assert_eq!(set.first(), Some(&30));
+        assert_eq!(set.last(), Some(&30));
+        assert_eq!(IndexSet::<i32>::new().first(), None);
+    }
+
+    #[test]
+    fn shift_remove_and_swap_remove() {
+        let original: IndexSet<i32> = (1..=5).collect();
+
+        let mut set = original.clone();
+        assert!(set.shift_remove(&2));
+        assert!(!set.shift_remove(&2));
+        assert_eq!(items(&set), vec![1, 3, 4, 5]);
+
+        let mut set = original.clone();
+        assert!(set.swap_remove(&2));
+        assert!(!set.swap_remove(&9));
+        assert_eq!(items(&set), vec![1, 5, 3, 4]);
+
+        let mut set = original.clone();
+        set.remove(&2);
+        set.remove(&2);
+        assert_eq!(items(&set), vec![1, 3, 4, 5]);
+    }
+
+    #[test]
+    fn shift_without_and_swap_without() {
+        let set: IndexSet<i32> = (1..=5).collect();
+
+        let (shifted, removed) = set.shift_without(&1);
+        assert!(removed);
+        assert_eq!(items(&shifted), vec![2, 3, 4, 5]);
+
+        let (swapped, removed) = set.swap_without(&1);
+        assert!(removed);
+        assert_eq!(items(&swapped), vec![5, 2, 3, 4]);
+
+        let (unchanged, removed) = set.shift_without(&9);
+        assert!(!removed);
+        assert_eq!(unchanged, set);
+
+        let (unchanged, removed) = set.swap_without(&9);
+        assert!(!removed);
+        assert_eq!(unchanged, set);
+
+        assert_eq!(items(&set), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn iterators_stay_exhausted() {
+        let set: IndexSet<i32> = (1..=4).collect();
+        let set = set.without(&1).without(&4);
+
+        let mut iter = set.iter();
+        assert_eq!(iter.by_ref().count(), 2);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        let mut iter = set.into_iter();
+        assert_eq!(iter.by_ref().collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+    }
+
+    #[test]
+    fn clones_share_structure() {
+        use std::rc::Rc;
+
+        let set: IndexSet<Rc<u32>> = (0..200).map(Rc::new).collect();
+        let copy = set.clone();
+        let mut target: IndexSet<Rc<u32>> = (0..10).map(Rc::new).collect();
+        target.clone_from(&set);
+
+        assert!(set.iter().all(|item| Rc::strong_count(item) == 1));
+        assert_eq!(target, set);
+        assert!(std::ptr::eq(copy.first().unwrap(), set.first().unwrap()));
+        assert!(std::ptr::eq(target.last().unwrap(), set.last().unwrap()));
+    }
+
+    #[test]
+    fn into_map_with_builds_values() {
+        let set: IndexSet<&str> = ["pear", "fig", "apple"].into_iter().collect();
+        let set = set.without(&"fig");
+
+        let lengths = set.into_map_with(|item| item.len());
+        let pairs: Vec<_> = lengths.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(pairs, vec![("pear", 4), ("apple", 5)]);
+        assert_eq!(lengths.get(&"apple"), Some(&5));
+        assert_eq!(lengths.get(&"fig"), None);
+        assert_eq!(lengths.keys_as_set(), set);
+    }
+
+    #[test]
+    fn map_and_as_slice_vec() {
+        let set: IndexSet<i32> = [3, -1, 4, 1, -5].into_iter().collect();
+        let set = set.without(&4);
+        assert_eq!(set.as_slice_vec(), vec![&3, &-1, &1, &-5]);
+
+        let strings = set.map(|i| format!("#{i}"));
+        assert_eq!(strings.as_slice_vec(), vec!["#3", "#-1", "#1", "#-5"]);
+        assert!(strings.contains(&"#1".to_string()));
+
+        let magnitudes = set.map(|i| i.abs());
+        assert_eq!(items(&magnitudes), vec![3, 1, 5]);
+        assert_eq!(magnitudes.get_index_of(&5), Some(2));
+    }
+
+    #[test]
+    fn count_if_any_all() {
+        let set: IndexSet<i32> = (1..=6).collect();
+        let set = set.without(&4);
+
+        assert_eq!(set.count_if(|i| i % 2 == 0), 2);
+
+        let mut calls = 0;
+        assert!(set.any(|i| {
+            calls += 1;
+            *i > 2
+        }));
+        assert_eq!(calls, 3);
+
+        calls = 0;
+        assert!(!set.all(|i| {
+            calls += 1;
+            *i < 2
+        }));
+        assert_eq!(calls, 2);
+
+        assert!(set.all(|i| *i != 4));
+        assert!(!set.any(|i| *i > 6));
+    }
+
+    #[test]
+    fn union_order() {
+        let a: IndexSet<i32> = [3, 1, 2].into_iter().collect();
+        let b: IndexSet<i32> = [4, 2, 5, 3].into_iter().collect();
+
+        assert_eq!(items(&a.union(&b)), vec![3, 1, 2, 4, 5]);
+        assert_eq!(items(&b.union(&a)), vec![4, 2, 5, 3, 1]);
+        assert_eq!(items(&a.union(&a)), vec![3, 1, 2]);
+
+        let c: IndexSet<i32> = [7, 8].into_iter().collect();
+        assert_eq!(items(&a.union(&c)), vec![3, 1, 2, 7, 8]);
+    }
+
+    #[test]
+    fn intersection_order() {
+        let a: IndexSet<i32> = [3, 1, 2, 6].into_iter().collect();
+        let b: IndexSet<i32> = [6, 2, 5, 3].into_iter().collect();
+
+        assert_eq!(items(&a.intersection(&b)), vec![3, 2, 6]);
+        assert_eq!(items(&b.intersection(&a)), vec![6, 2, 3]);
+        assert_eq!(items(&a.intersection(&a)), vec![3, 1, 2, 6]);
+
+        let c: IndexSet<i32> = [7, 8].into_iter().collect();
+        assert!(a.intersection(&c).is_empty());
+    }
+
+    #[test]
+    fn difference_order() {
+        let a: IndexSet<i32> = [3, 1, 2, 6].into_iter().collect();
+        let b: IndexSet<i32> = [6, 2, 5].into_iter().collect();
+
+        assert_eq!(items(&a.difference(&b)), vec![3, 1]);
+        assert_eq!(items(&b.difference(&a)), vec![5]);
+        assert!(a.difference(&a).is_empty());
+
+        let c: IndexSet<i32> = [7, 8].into_iter().collect();
+        assert_eq!(items(&a.difference(&c)), vec![3, 1, 2, 6]);
+
+        #[allow(deprecated)]
+        let complement = a.clone().relative_complement(b.clone());
+        assert_eq!(complement, a.difference(&b));
+    }
+
+    #[test]
+    fn symmetric_difference_order() {
+        let a: IndexSet<i32> = [3, 1, 2, 6].into_iter().collect();
+        let b: IndexSet<i32> = [6, 2, 5, 4].into_iter().collect();
+
+        assert_eq!(items(&a.symmetric_difference(&b)), vec![3, 1, 5, 4]);
+        assert!(a.symmetric_difference(&a).is_empty());
+
+        let c: IndexSet<i32> = [7, 8].into_iter().collect();
+        assert_eq!(items(&a.symmetric_difference(&c)), vec![3, 1, 2, 6, 7, 8]);
+    }
 }